@@ -0,0 +1,220 @@
+use std::io::{stderr, stdin, stdout, Read};
+
+use argparse::{ArgumentParser, Store, StoreTrue};
+use prettytable::{row, Table};
+
+use crate::{
+    secrets::allowlist::{load_allowlist, migrate_plaintext, parse_expiry, AllowlistEntry},
+    store::{self, StoreConfig},
+    utils::common::print_error,
+};
+
+pub async fn run(args: Vec<String>) {
+    let mut action = String::new();
+    let mut id = String::new();
+    let mut store_uri = String::new();
+    let mut db_name = String::new();
+    let mut db_collections = String::new();
+    let mut secret = String::new();
+    let mut detector = String::new();
+    let mut path = String::new();
+    let mut expires = String::new();
+    let mut reason = String::new();
+    let mut dry_run = false;
+    let mut keep_plaintext = false;
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description(
+            "Manage the secret allowlist, secrets are stored as salted SHA-256 hashes only",
+        );
+        ap.refer(&mut action).required().add_argument(
+            "action",
+            Store,
+            "add, remove, list or migrate (hash the plaintext whitelist of older versions)",
+        );
+        ap.refer(&mut id)
+            .add_argument("id", Store, "Id of the entry to remove");
+        ap.refer(&mut store_uri).required().add_option(
+            &["-o", "--mongo-uri", "--store-uri"],
+            Store,
+            "Pass the uri of the finding store the allowlist is stored in (mongodb:// or sqlite://<path>)",
+        );
+        ap.refer(&mut db_name).add_option(
+            &["--db-name"],
+            Store,
+            "Name of the database in the finding store",
+        );
+        ap.refer(&mut db_collections).add_option(
+            &["--db-collections"],
+            Store,
+            "Override collection names, e.g. allowlist=hela_allowlist,secrets=hela_secrets",
+        );
+        ap.refer(&mut secret).add_option(
+            &["--secret"],
+            Store,
+            "Secret to allow, read from stdin if not given (keeps it out of the shell history)",
+        );
+        ap.refer(&mut detector).add_option(
+            &["--detector"],
+            Store,
+            "Allow the secret only for this detector",
+        );
+        ap.refer(&mut path).add_option(
+            &["--path"],
+            Store,
+            "Allow the secret only in files matching this glob, relative to the project root",
+        );
+        ap.refer(&mut expires).add_option(
+            &["--expires"],
+            Store,
+            "Date (YYYY-MM-DD) or RFC 3339 timestamp after which the entry stops applying",
+        );
+        ap.refer(&mut reason).add_option(
+            &["--reason"],
+            Store,
+            "Why the secret is allowed, shown by list",
+        );
+        ap.refer(&mut dry_run).add_option(
+            &["--dry-run"],
+            StoreTrue,
+            "Only count the plaintext entries migrate would hash",
+        );
+        ap.refer(&mut keep_plaintext).add_option(
+            &["--keep-plaintext"],
+            StoreTrue,
+            "Don't delete the plaintext entries after migrating them",
+        );
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
+    }
+
+    let store = match StoreConfig::new(&db_name, &db_collections) {
+        Ok(config) => store::open(&store_uri, &config).await,
+        Err(e) => Err(e),
+    };
+    let store = match store {
+        Ok(store) => store,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to open the finding store: {}", e),
+                1,
+            );
+            return;
+        }
+    };
+
+    match action.as_str() {
+        "add" => {
+            if secret.is_empty() {
+                let mut input = String::new();
+                if let Err(e) = stdin().read_to_string(&mut input) {
+                    print_error(
+                        &format!("Error: Unable to read secret from stdin: {}", e),
+                        1,
+                    );
+                }
+                secret = input.trim_end_matches(['\r', '\n']).to_string();
+            }
+            if secret.is_empty() {
+                print_error(
+                    "Error: No secret given, pass --secret or pipe it to stdin",
+                    1,
+                );
+            }
+            let expires_at = if expires.is_empty() {
+                None
+            } else {
+                match parse_expiry(&expires) {
+                    Ok(expires_at) => Some(expires_at),
+                    Err(e) => {
+                        print_error(&format!("Error: {}", e), 1);
+                        return;
+                    }
+                }
+            };
+            let entry = AllowlistEntry::new(&secret, &detector, &path, expires_at, &reason);
+            if let Err(e) = entry.path_matcher() {
+                print_error(&format!("Error: {}", e), 1);
+            }
+            if let Err(e) = store.add_allowlist_entry(&entry).await {
+                print_error(&format!("Error: Unable to add allowlist entry: {}", e), 1);
+            }
+            println!("[+] Added allowlist entry {}", entry.id);
+        }
+        "remove" => {
+            if id.is_empty() {
+                print_error("Error: Pass the id of the entry to remove", 1);
+            }
+            match store.remove_allowlist_entry(&id).await {
+                Ok(true) => println!("[+] Removed allowlist entry {}", id),
+                Ok(false) => print_error(&format!("Error: No allowlist entry {}", id), 1),
+                Err(e) => print_error(
+                    &format!("Error: Unable to remove allowlist entry: {}", e),
+                    1,
+                ),
+            }
+        }
+        "list" => {
+            let allowlist = match load_allowlist(store.as_ref()).await {
+                Ok(allowlist) => allowlist,
+                Err(e) => {
+                    print_error(&format!("Error: Unable to load the allowlist: {}", e), 1);
+                    return;
+                }
+            };
+            if allowlist.entries.is_empty() {
+                println!("[+] The secret allowlist is empty");
+                return;
+            }
+            let mut table = Table::new();
+            table.add_row(
+                row![bFg->"ID", bFg->"Detector", bFg->"Path", bFg->"Expires", bFg->"Status", bFg->"Reason", bFg->"Created"],
+            );
+            for entry in allowlist.entries.iter() {
+                table.add_row(row![
+                    entry.id,
+                    if entry.detector.is_empty() {
+                        "Any"
+                    } else {
+                        &entry.detector
+                    },
+                    if entry.path.is_empty() {
+                        "Any"
+                    } else {
+                        &entry.path
+                    },
+                    entry
+                        .expires_at
+                        .map(|x| x.to_rfc3339())
+                        .unwrap_or("Never".to_string()),
+                    if entry.is_expired() {
+                        "Expired"
+                    } else {
+                        "Active"
+                    },
+                    entry.reason,
+                    entry.created_at
+                ]);
+            }
+            table.printstd();
+        }
+        "migrate" => match migrate_plaintext(store.as_ref(), dry_run, keep_plaintext).await {
+            Ok(count) if dry_run => {
+                println!(
+                    "[+] {} plaintext whitelist entries would be migrated",
+                    count
+                )
+            }
+            Ok(count) => println!("[+] Migrated {} plaintext whitelist entries", count),
+            Err(e) => print_error(&format!("Error: Migration failed: {}", e), 1),
+        },
+        _ => print_error(
+            &format!(
+                "Error: Unknown allowlist action {}, use add, remove, list or migrate",
+                action
+            ),
+            1,
+        ),
+    }
+}
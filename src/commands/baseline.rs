@@ -0,0 +1,276 @@
+use std::io::{stderr, stdout};
+
+use argparse::{ArgumentParser, Store, StoreTrue};
+
+use crate::{
+    scans::{
+        scanner::ScanRunner,
+        suppressions::Suppressions,
+        tools::{
+            license_tool::LicenseTool,
+            sast_tool::SastTool,
+            sca_tool::ScaTool,
+            secret_tool::{SecretEngineMode, SecretOptions, SecretTool},
+        },
+    },
+    utils::{
+        baseline::{Baseline, DEFAULT_BASELINE_FILE},
+        common::{prepare_checkout, print_error},
+        git_auth::{self, AuthOptions},
+    },
+};
+
+pub async fn run(args: Vec<String>) {
+    let mut action = String::new();
+    let mut path = String::new();
+    let mut branch = String::new();
+    let mut output = String::new();
+    let mut is_sast = false;
+    let mut is_sca = false;
+    let mut is_secret = false;
+    let mut rule_path = String::new();
+    let mut secret_engine = "auto".to_string();
+    let mut secret_rules = String::new();
+    let mut no_install = false;
+    let mut root_only = false;
+    let mut build_args = String::new();
+    let mut manifests = String::new();
+    let mut verbose = false;
+    let mut auth_options = AuthOptions::default();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description(
+            "Create or update a baseline file of known findings, scans with --baseline only report findings which are not in it",
+        );
+        ap.refer(&mut action)
+            .required()
+            .add_argument("action", Store, "create or update");
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Enable verbose mode!");
+        ap.refer(&mut path).required().add_option(
+            &["-p", "--code-path"],
+            Store,
+            "Pass the path of the project (Local Path or HTTP Git URL)",
+        );
+        ap.refer(&mut branch).add_option(
+            &["--branch"],
+            Store,
+            "Specify the branch to create the baseline for",
+        );
+        ap.refer(&mut output).add_option(
+            &["-o", "--output"],
+            Store,
+            "Path of the baseline file (default: .hela-baseline.json in the project for a local path, in the current directory otherwise)",
+        );
+        ap.refer(&mut is_sast)
+            .add_option(&["-s", "--sast"], StoreTrue, "Include SAST findings");
+        ap.refer(&mut is_sca)
+            .add_option(&["-c", "--sca"], StoreTrue, "Include SCA findings");
+        ap.refer(&mut is_secret).add_option(
+            &["-e", "--secret"],
+            StoreTrue,
+            "Include Secret findings",
+        );
+        ap.refer(&mut rule_path).add_option(
+            &["-t", "--rule-path"],
+            Store,
+            "Pass the path of the SAST rules to use (Local Path or HTTP Git URL)",
+        );
+        ap.refer(&mut secret_engine).add_option(
+            &["--secret-engine"],
+            Store,
+            "Secret detection engine to use, supports auto, native, trufflehog and both (default: auto)",
+        );
+        ap.refer(&mut secret_rules).add_option(
+            &["--secret-rules"],
+            Store,
+            "Pass the secret rules file with detector, value and path filters (Local Path or HTTP URL)",
+        );
+        ap.refer(&mut no_install).add_option(
+            &["-n", "--no-install"],
+            StoreTrue,
+            "Skip installing dependencies",
+        );
+        ap.refer(&mut root_only).add_option(
+            &["-r", "--root-only"],
+            StoreTrue,
+            "Scan manifests only in the root directory, don't look for manifests in subdirectories",
+        );
+        ap.refer(&mut build_args).add_option(
+            &["-d", "--build-args"],
+            Store,
+            "Pass the build context args to scan",
+        );
+        ap.refer(&mut manifests).add_option(
+            &["-m", "--manifests"],
+            Store,
+            "Specify manifest files to scan",
+        );
+        git_auth::add_options(&mut ap, &mut auth_options);
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
+    }
+
+    // credentials are applied to the git commands, the code path keeps none
+    match git_auth::setup(&path, &auth_options).await {
+        Ok(clean_path) => path = clean_path,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to set up git authentication: {}", e),
+                1,
+            );
+            return;
+        }
+    }
+    if action != "create" && action != "update" {
+        print_error(
+            &format!(
+                "Error: Unknown baseline action {}, use create or update",
+                action
+            ),
+            1,
+        );
+    }
+    let engine = match SecretEngineMode::from_name(&secret_engine) {
+        Some(engine) => engine,
+        None => {
+            print_error(
+                &format!(
+                    "Error: Unsupported secret engine {}, use auto, native, trufflehog or both",
+                    secret_engine
+                ),
+                1,
+            );
+            return;
+        }
+    };
+    // without scan flags the baseline covers every scan with findings
+    if !is_sast && !is_sca && !is_secret {
+        is_sast = true;
+        is_sca = true;
+        is_secret = true;
+    }
+
+    // a baseline belongs into the repository it was created for
+    if output.is_empty() {
+        output = if std::path::Path::new(&path).is_dir() {
+            format!("{}/{}", path.trim_end_matches('/'), DEFAULT_BASELINE_FILE)
+        } else {
+            DEFAULT_BASELINE_FILE.to_string()
+        };
+    }
+    let output = match std::env::current_dir() {
+        Ok(dir) => dir.join(&output).to_string_lossy().to_string(),
+        Err(_) => output,
+    };
+    let exists = std::path::Path::new(&output).exists();
+    if action == "create" && exists {
+        print_error(
+            &format!(
+                "Error: Baseline {} already exists, use `hela baseline update` to refresh it",
+                output
+            ),
+            1,
+        );
+    }
+    if action == "update" && !exists {
+        print_error(
+            &format!(
+                "Error: Baseline {} doesn't exist, use `hela baseline create` first",
+                output
+            ),
+            1,
+        );
+    }
+
+    // results of an earlier run must not end up in the baseline
+    let _ = std::fs::remove_file("/tmp/output.json");
+    let suppressions = Suppressions::new(None);
+    let scanner = ScanRunner::new(
+        SastTool::new(),
+        ScaTool::new(),
+        SecretTool::with_options(SecretOptions {
+            engine,
+            rules: secret_rules,
+            ..Default::default()
+        }),
+        LicenseTool::new(),
+    );
+    // a local project is checked out up front without ignored files, a branch of a remote
+    // project is cloned by the scans
+    let local = !path.starts_with("http");
+    let reference = if local { branch.clone() } else { String::new() };
+    if let Err(e) = prepare_checkout(&path, "/tmp/app", &reference, false) {
+        print_error(&format!("Error: {}", e), 1);
+        return;
+    }
+    let branch = if branch.is_empty() || local {
+        None
+    } else {
+        Some(branch.as_str())
+    };
+    for (enabled, scan_type) in [(is_sast, "sast"), (is_sca, "sca"), (is_secret, "secret")] {
+        if enabled {
+            scanner
+                .execute_scan(
+                    &suppressions,
+                    scan_type,
+                    &path,
+                    None,
+                    branch,
+                    no_install,
+                    root_only,
+                    build_args.clone(),
+                    manifests.clone(),
+                    rule_path.clone(),
+                    verbose,
+                )
+                .await;
+        }
+    }
+
+    let output_json = match std::fs::read_to_string("/tmp/output.json") {
+        Ok(data) => serde_json::from_str::<serde_json::Value>(&data).unwrap_or_default(),
+        Err(_) => serde_json::Value::Null,
+    };
+    let current = Baseline::from_output(&output_json, "/tmp/app");
+    if action == "create" {
+        if let Err(e) = current.save(&output) {
+            print_error(
+                &format!("Error: Unable to write baseline to {}: {}", output, e),
+                1,
+            );
+        }
+        println!(
+            "[+] Baseline with {} findings created at {}",
+            current.findings.len(),
+            output
+        );
+        return;
+    }
+    let mut baseline = match Baseline::load(&output) {
+        Ok(baseline) => baseline,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to read baseline {}: {}", output, e),
+                1,
+            );
+            return;
+        }
+    };
+    let (added, removed) = baseline.update(current);
+    if let Err(e) = baseline.save(&output) {
+        print_error(
+            &format!("Error: Unable to write baseline to {}: {}", output, e),
+            1,
+        );
+    }
+    println!(
+        "[+] Baseline {} updated, {} findings added, {} removed, {} in total",
+        output,
+        added,
+        removed,
+        baseline.findings.len()
+    );
+}
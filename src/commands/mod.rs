@@ -0,0 +1,5 @@
+pub mod allowlist;
+pub mod baseline;
+pub mod notices;
+pub mod sbom;
+pub mod triage;
@@ -0,0 +1,180 @@
+use std::io::{stderr, stdout};
+
+use argparse::{ArgumentParser, Store, StoreTrue};
+
+use crate::{
+    license::notices::{collect_notices, to_html, to_markdown, to_text},
+    sbom::project_name,
+    scans::{
+        scanner::ScanRunner,
+        suppressions::Suppressions,
+        tools::{
+            license_tool::{LicenseTool, LICENSE_FILES_KEY},
+            sast_tool::SastTool,
+            sca_tool::ScaTool,
+            secret_tool::SecretTool,
+        },
+    },
+    utils::{
+        common::{prepare_checkout, print_error},
+        git_auth::{self, AuthOptions},
+    },
+};
+
+pub async fn run(args: Vec<String>) {
+    let mut path = String::new();
+    let mut branch = String::new();
+    let mut output = String::new();
+    let mut format = "markdown".to_string();
+    let mut no_install = false;
+    let mut root_only = false;
+    let mut build_args = String::new();
+    let mut manifests = String::new();
+    let mut verbose = false;
+    let mut auth_options = AuthOptions::default();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Generate a third-party notices (attribution) file for the project");
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Enable verbose mode!");
+        ap.refer(&mut path).required().add_option(
+            &["-p", "--code-path"],
+            Store,
+            "Pass the path of the project (Local Path or HTTP Git URL)",
+        );
+        ap.refer(&mut branch).add_option(
+            &["--branch"],
+            Store,
+            "Specify the branch to generate notices for",
+        );
+        ap.refer(&mut output).add_option(
+            &["-o", "--output"],
+            Store,
+            "Path of the notices file to write (default: THIRD_PARTY_NOTICES.md, THIRD_PARTY_NOTICES.txt or THIRD_PARTY_NOTICES.html)",
+        );
+        ap.refer(&mut format).add_option(
+            &["-f", "--format"],
+            Store,
+            "Format of the notices file, supports markdown, text and html",
+        );
+        ap.refer(&mut no_install).add_option(
+            &["-n", "--no-install"],
+            StoreTrue,
+            "Skip installing dependencies",
+        );
+        ap.refer(&mut root_only).add_option(
+            &["-r", "--root-only"],
+            StoreTrue,
+            "Scan manifests only in the root directory, don't look for manifests in subdirectories",
+        );
+        ap.refer(&mut build_args).add_option(
+            &["-d", "--build-args"],
+            Store,
+            "Pass the build context args to scan",
+        );
+        ap.refer(&mut manifests).add_option(
+            &["-m", "--manifests"],
+            Store,
+            "Specify manifest files to scan",
+        );
+        git_auth::add_options(&mut ap, &mut auth_options);
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
+    }
+
+    // credentials are applied to the git commands, the code path keeps none
+    match git_auth::setup(&path, &auth_options).await {
+        Ok(clean_path) => path = clean_path,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to set up git authentication: {}", e),
+                1,
+            );
+            return;
+        }
+    }
+    let default_output = match format.as_str() {
+        "markdown" => "THIRD_PARTY_NOTICES.md",
+        "text" => "THIRD_PARTY_NOTICES.txt",
+        "html" => "THIRD_PARTY_NOTICES.html",
+        _ => {
+            print_error(
+                &format!(
+                    "Error: Unsupported notices format {}, use markdown, text or html",
+                    format
+                ),
+                1,
+            );
+            return;
+        }
+    };
+    if output.is_empty() {
+        output = default_output.to_string();
+    }
+    // if the output path is relative, it should be relative to where hela was started
+    // and not to the checkout in /tmp/app
+    let output = match std::env::current_dir() {
+        Ok(dir) => dir.join(&output).to_string_lossy().to_string(),
+        Err(_) => output,
+    };
+
+    let scanner = ScanRunner::new(
+        SastTool::new(),
+        ScaTool::new(),
+        SecretTool::new(),
+        LicenseTool::new(),
+    );
+    // a local project is checked out up front without ignored files, a branch of a remote
+    // project is cloned by the scans
+    let local = !path.starts_with("http");
+    let reference = if local { branch.clone() } else { String::new() };
+    if let Err(e) = prepare_checkout(&path, "/tmp/app", &reference, false) {
+        print_error(&format!("Error: {}", e), 1);
+        return;
+    }
+    let branch = if branch.is_empty() || local {
+        None
+    } else {
+        Some(branch.as_str())
+    };
+    scanner
+        .execute_scan(
+            &Suppressions::new(None),
+            "license-compliance",
+            &path,
+            branch,
+            None,
+            no_install,
+            root_only,
+            build_args,
+            manifests,
+            String::new(),
+            verbose,
+        )
+        .await;
+
+    let output_json = match std::fs::read_to_string("/tmp/output.json") {
+        Ok(data) => serde_json::from_str::<serde_json::Value>(&data).unwrap_or_default(),
+        Err(_) => serde_json::Value::Null,
+    };
+    let groups = collect_notices(&output_json, "/tmp/app", LICENSE_FILES_KEY);
+    let project = project_name(&path);
+    let notices = match format.as_str() {
+        "text" => to_text(&groups, &project),
+        "html" => to_html(&groups, &project),
+        _ => to_markdown(&groups, &project),
+    };
+    if let Err(e) = std::fs::write(&output, notices) {
+        print_error(
+            &format!("Error: Unable to write notices to {}: {}", output, e),
+            1,
+        );
+    }
+    println!(
+        "[+] Third-party notices for {} components under {} licenses generated at {}",
+        groups.iter().map(|x| x.notices.len()).sum::<usize>(),
+        groups.len(),
+        output
+    );
+}
@@ -0,0 +1,228 @@
+use std::io::{stderr, stdout};
+
+use argparse::{ArgumentParser, Store, StoreFalse, StoreTrue};
+
+use crate::{
+    sbom::{cyclonedx, inventory_from_output, project_name, spdx},
+    scans::{
+        scanner::ScanRunner,
+        suppressions::Suppressions,
+        tools::{
+            license_tool::LicenseTool, sast_tool::SastTool, sca_tool::ScaTool,
+            secret_tool::SecretTool,
+        },
+    },
+    utils::{
+        common::{prepare_checkout, print_error},
+        git_auth::{self, AuthOptions},
+    },
+};
+
+pub async fn run(args: Vec<String>) {
+    let mut path = String::new();
+    let mut branch = String::new();
+    let mut commit_id = String::new();
+    let mut output = String::new();
+    let mut format = "json".to_string();
+    let mut include_vulns = true;
+    let mut no_install = false;
+    let mut root_only = false;
+    let mut build_args = String::new();
+    let mut manifests = String::new();
+    let mut verbose = false;
+    let mut auth_options = AuthOptions::default();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Generate a CycloneDX or SPDX SBOM for the whole project");
+        ap.refer(&mut verbose)
+            .add_option(&["-v", "--verbose"], StoreTrue, "Enable verbose mode!");
+        ap.refer(&mut path).required().add_option(
+            &["-p", "--code-path"],
+            Store,
+            "Pass the path of the project (Local Path or HTTP Git URL)",
+        );
+        ap.refer(&mut branch).add_option(
+            &["--branch"],
+            Store,
+            "Specify the branch to generate SBOM for",
+        );
+        ap.refer(&mut commit_id).add_option(
+            &["-i", "--commit", "--commit-id"],
+            Store,
+            "Generate the SBOM for this commit (or tag)",
+        );
+        ap.refer(&mut output).add_option(
+            &["-o", "--output"],
+            Store,
+            "Path of the SBOM file to write (default: sbom.cdx.json, sbom.cdx.xml, sbom.spdx.json or sbom.spdx)",
+        );
+        ap.refer(&mut format).add_option(
+            &["-f", "--format"],
+            Store,
+            "SBOM format to write, supports json and xml (CycloneDX), spdx-json and spdx (SPDX tag-value)",
+        );
+        ap.refer(&mut include_vulns).add_option(
+            &["--no-vulns"],
+            StoreFalse,
+            "Skip SCA scan, SBOM won't contain vulnerabilities (VEX)",
+        );
+        ap.refer(&mut no_install).add_option(
+            &["-n", "--no-install"],
+            StoreTrue,
+            "Skip installing dependencies",
+        );
+        ap.refer(&mut root_only).add_option(
+            &["-r", "--root-only"],
+            StoreTrue,
+            "Scan manifests only in the root directory, don't look for manifests in subdirectories",
+        );
+        ap.refer(&mut build_args).add_option(
+            &["-d", "--build-args"],
+            Store,
+            "Pass the build context args to scan",
+        );
+        ap.refer(&mut manifests).add_option(
+            &["-m", "--manifests"],
+            Store,
+            "Specify manifest files to scan",
+        );
+        git_auth::add_options(&mut ap, &mut auth_options);
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
+    }
+
+    // credentials are applied to the git commands, the code path keeps none
+    match git_auth::setup(&path, &auth_options).await {
+        Ok(clean_path) => path = clean_path,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to set up git authentication: {}", e),
+                1,
+            );
+            return;
+        }
+    }
+    let default_output = match format.as_str() {
+        "json" => "sbom.cdx.json",
+        "xml" => "sbom.cdx.xml",
+        "spdx-json" => "sbom.spdx.json",
+        "spdx" => "sbom.spdx",
+        _ => {
+            print_error(
+                &format!(
+                    "Error: Unsupported SBOM format {}, use json, xml, spdx-json or spdx",
+                    format
+                ),
+                1,
+            );
+            return;
+        }
+    };
+    if output.is_empty() {
+        output = default_output.to_string();
+    }
+    // if the output path is relative, it should be relative to where hela was started
+    // and not to the checkout in /tmp/app
+    let output = match std::env::current_dir() {
+        Ok(dir) => dir.join(&output).to_string_lossy().to_string(),
+        Err(_) => output,
+    };
+
+    // a local project and a commit are checked out up front without ignored files, a
+    // branch of a remote project is cloned by the license scan
+    let reference = if !commit_id.is_empty() {
+        if !branch.is_empty() {
+            print_error(
+                "Warning: --branch is ignored, the SBOM is generated for --commit",
+                101,
+            );
+        }
+        commit_id.clone()
+    } else if !path.starts_with("http") {
+        branch.clone()
+    } else {
+        String::new()
+    };
+    if let Err(e) = prepare_checkout(&path, "/tmp/app", &reference, false) {
+        print_error(&format!("Error: {}", e), 1);
+        return;
+    }
+
+    let suppressions = Suppressions::new(None);
+    let scanner = ScanRunner::new(
+        SastTool::new(),
+        ScaTool::new(),
+        SecretTool::new(),
+        LicenseTool::new(),
+    );
+    let branch = if branch.is_empty() || !reference.is_empty() {
+        None
+    } else {
+        Some(branch.as_str())
+    };
+    // license scan runs first as it is the one cloning the requested branch, SCA
+    // reuses the same checkout from /tmp/app
+    scanner
+        .execute_scan(
+            &suppressions,
+            "license-compliance",
+            &path,
+            branch,
+            None,
+            no_install,
+            root_only,
+            build_args.clone(),
+            manifests.clone(),
+            String::new(),
+            verbose,
+        )
+        .await;
+    if include_vulns {
+        scanner
+            .execute_scan(
+                &suppressions,
+                "sca",
+                &path,
+                None,
+                None,
+                no_install,
+                root_only,
+                build_args.clone(),
+                manifests.clone(),
+                String::new(),
+                verbose,
+            )
+            .await;
+    }
+
+    let output_json = match std::fs::read_to_string("/tmp/output.json") {
+        Ok(data) => serde_json::from_str::<serde_json::Value>(&data).unwrap_or_default(),
+        Err(_) => serde_json::Value::Null,
+    };
+    let inventory = inventory_from_output(&output_json);
+    let project = project_name(&path);
+    let sbom = match format.as_str() {
+        "xml" => cyclonedx::to_xml(&inventory, &project),
+        "spdx-json" => serde_json::to_string_pretty(&spdx::to_json(&inventory, &project)).unwrap(),
+        "spdx" => spdx::to_tag_value(&inventory, &project),
+        _ => serde_json::to_string_pretty(&cyclonedx::to_json(&inventory, &project)).unwrap(),
+    };
+    if let Err(e) = std::fs::write(&output, sbom) {
+        print_error(
+            &format!("Error: Unable to write SBOM to {}: {}", output, e),
+            1,
+        );
+    }
+    println!(
+        "[+] {} SBOM with {} components and {} vulnerabilities generated at {}",
+        if format.starts_with("spdx") {
+            "SPDX"
+        } else {
+            "CycloneDX"
+        },
+        inventory.components.len(),
+        inventory.vulnerabilities.len(),
+        output
+    );
+}
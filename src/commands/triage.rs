@@ -0,0 +1,208 @@
+use std::io::{stderr, stdout};
+
+use argparse::{ArgumentParser, Store};
+use chrono::Utc;
+use prettytable::{row, Table};
+
+use crate::{
+    secrets::allowlist::parse_expiry,
+    store::{self, StoreConfig, TriageRecord},
+    utils::{
+        common::print_error,
+        triage::{is_dismissal, is_expired, STATES},
+    },
+};
+
+fn print_records(records: &[TriageRecord]) {
+    let mut table = Table::new();
+    table.add_row(
+        row![bFg->"Fingerprint", bFg->"State", bFg->"Owner", bFg->"Expires", bFg->"Justification", bFg->"Updated"],
+    );
+    for record in records {
+        let expires = if record.expires_at.is_empty() {
+            "Never".to_string()
+        } else if is_expired(record) {
+            format!("{} (expired)", record.expires_at)
+        } else {
+            record.expires_at.clone()
+        };
+        table.add_row(row![
+            record.hash,
+            record.state,
+            record.owner,
+            expires,
+            record.justification,
+            record.updated_at
+        ]);
+    }
+    table.printstd();
+}
+
+pub async fn run(args: Vec<String>) {
+    let mut action = String::new();
+    let mut fingerprint = String::new();
+    let mut store_uri = String::new();
+    let mut db_name = String::new();
+    let mut db_collections = String::new();
+    let mut state = String::new();
+    let mut justification = String::new();
+    let mut owner = String::new();
+    let mut expires = String::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description(
+            "Triage findings by their fingerprint, false positives and accepted risks don't count towards the policy until they expire",
+        );
+        ap.refer(&mut action)
+            .required()
+            .add_argument("action", Store, "set, show or list");
+        ap.refer(&mut fingerprint).add_argument(
+            "fingerprint",
+            Store,
+            "Fingerprint of the finding (see the --json output or SARIF partialFingerprints)",
+        );
+        ap.refer(&mut store_uri).required().add_option(
+            &["-o", "--mongo-uri", "--store-uri"],
+            Store,
+            "Pass the uri of the finding store the triage states are stored in (mongodb:// or sqlite://<path>)",
+        );
+        ap.refer(&mut db_name).add_option(
+            &["--db-name"],
+            Store,
+            "Name of the database in the finding store",
+        );
+        ap.refer(&mut db_collections).add_option(
+            &["--db-collections"],
+            Store,
+            "Override collection names, e.g. triage=hela_triage",
+        );
+        ap.refer(&mut state).add_option(
+            &["--state"],
+            Store,
+            "open, confirmed, false-positive, accepted-risk or fixed (filters the list)",
+        );
+        ap.refer(&mut justification).add_option(
+            &["--justification"],
+            Store,
+            "Why the finding has the state, required for false-positive and accepted-risk",
+        );
+        ap.refer(&mut owner).add_option(
+            &["--owner"],
+            Store,
+            "Person or team responsible for the finding",
+        );
+        ap.refer(&mut expires).add_option(
+            &["--expires"],
+            Store,
+            "Date (YYYY-MM-DD) or RFC 3339 timestamp after which the state stops applying, required for accepted-risk",
+        );
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
+    }
+    if !state.is_empty() && !STATES.contains(&state.as_str()) {
+        print_error(
+            &format!(
+                "Error: Unknown triage state {}, use {}",
+                state,
+                STATES.join(", ")
+            ),
+            1,
+        );
+    }
+
+    let store = match StoreConfig::new(&db_name, &db_collections) {
+        Ok(config) => store::open(&store_uri, &config).await,
+        Err(e) => Err(e),
+    };
+    let store = match store {
+        Ok(store) => store,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to open the finding store: {}", e),
+                1,
+            );
+            return;
+        }
+    };
+
+    match action.as_str() {
+        "set" => {
+            if fingerprint.is_empty() || state.is_empty() {
+                print_error(
+                    "Error: Pass the fingerprint of the finding and its --state",
+                    1,
+                );
+            }
+            if is_dismissal(&state) && justification.is_empty() {
+                print_error(
+                    &format!("Error: State {} needs a --justification", state),
+                    1,
+                );
+            }
+            // an accepted risk has to be looked at again eventually
+            if state == "accepted-risk" && expires.is_empty() {
+                print_error("Error: State accepted-risk needs an --expires date", 1);
+            }
+            let expires_at = if expires.is_empty() {
+                String::new()
+            } else {
+                match parse_expiry(&expires) {
+                    Ok(expires_at) if expires_at <= Utc::now() => {
+                        print_error(&format!("Error: Expiry {} is in the past", expires), 1);
+                        return;
+                    }
+                    Ok(expires_at) => expires_at.to_rfc3339(),
+                    Err(e) => {
+                        print_error(&format!("Error: {}", e), 1);
+                        return;
+                    }
+                }
+            };
+            let record = TriageRecord {
+                hash: fingerprint.clone(),
+                state: state.clone(),
+                justification,
+                owner,
+                expires_at,
+                updated_at: Utc::now().to_rfc3339(),
+            };
+            if let Err(e) = store.set_triage_record(&record).await {
+                print_error(&format!("Error: Unable to set triage state: {}", e), 1);
+            }
+            println!("[+] Finding {} is now {}", fingerprint, state);
+        }
+        "show" | "list" => {
+            if action == "show" && fingerprint.is_empty() {
+                print_error("Error: Pass the fingerprint of the finding to show", 1);
+            }
+            let hashes = [fingerprint.clone()];
+            let filter = if action == "show" {
+                Some(&hashes[..])
+            } else {
+                None
+            };
+            let mut records = match store.triage_records(filter).await {
+                Ok(records) => records,
+                Err(e) => {
+                    print_error(&format!("Error: Unable to load triage states: {}", e), 1);
+                    return;
+                }
+            };
+            records.retain(|x| state.is_empty() || x.state == state);
+            if records.is_empty() {
+                println!("[+] No triaged findings");
+                return;
+            }
+            records.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+            print_records(&records);
+        }
+        _ => print_error(
+            &format!(
+                "Error: Unknown triage action {}, use set, show or list",
+                action
+            ),
+            1,
+        ),
+    }
+}
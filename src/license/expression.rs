@@ -0,0 +1,448 @@
+use std::fmt;
+
+// SPDX license identifiers Hela knows about, used to fix the casing of IDs reported by
+// package managers (e.g. "apache-2.0" -> "Apache-2.0")
+const SPDX_IDS: [&str; 96] = [
+    "0BSD",
+    "AFL-3.0",
+    "AGPL-1.0-only",
+    "AGPL-1.0-or-later",
+    "AGPL-3.0",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-1.0",
+    "Apache-1.1",
+    "Apache-2.0",
+    "APSL-2.0",
+    "Artistic-1.0",
+    "Artistic-2.0",
+    "BlueOak-1.0.0",
+    "BSD-1-Clause",
+    "BSD-2-Clause",
+    "BSD-2-Clause-Patent",
+    "BSD-3-Clause",
+    "BSD-3-Clause-Clear",
+    "BSD-4-Clause",
+    "BSL-1.0",
+    "BUSL-1.1",
+    "CAL-1.0",
+    "CC-BY-3.0",
+    "CC-BY-4.0",
+    "CC-BY-NC-4.0",
+    "CC-BY-NC-SA-4.0",
+    "CC-BY-ND-4.0",
+    "CC-BY-SA-3.0",
+    "CC-BY-SA-4.0",
+    "CC0-1.0",
+    "CDDL-1.0",
+    "CDDL-1.1",
+    "CECILL-2.1",
+    "CPAL-1.0",
+    "CPL-1.0",
+    "ECL-2.0",
+    "EPL-1.0",
+    "EPL-2.0",
+    "EUPL-1.1",
+    "EUPL-1.2",
+    "GFDL-1.3-only",
+    "GFDL-1.3-or-later",
+    "GPL-1.0-only",
+    "GPL-1.0-or-later",
+    "GPL-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "HPND",
+    "ICU",
+    "IJG",
+    "ISC",
+    "JSON",
+    "LGPL-2.0-only",
+    "LGPL-2.0-or-later",
+    "LGPL-2.1",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "LPL-1.02",
+    "MIT",
+    "MIT-0",
+    "MIT-CMU",
+    "MPL-1.0",
+    "MPL-1.1",
+    "MPL-2.0",
+    "MPL-2.0-no-copyleft-exception",
+    "MS-PL",
+    "MS-RL",
+    "NCSA",
+    "ODbL-1.0",
+    "OFL-1.1",
+    "OpenSSL",
+    "OSL-3.0",
+    "PHP-3.01",
+    "PostgreSQL",
+    "PSF-2.0",
+    "Python-2.0",
+    "Ruby",
+    "SISSL",
+    "SSPL-1.0",
+    "Unicode-DFS-2016",
+    "Unicode-3.0",
+    "Unlicense",
+    "UPL-1.0",
+    "W3C",
+    "WTFPL",
+    "X11",
+    "Zlib",
+    "ZPL-2.1",
+    "Elastic-2.0",
+];
+
+// Common license names used in package metadata which are not SPDX IDs, keys are
+// compared after lowercasing and removing everything except letters, digits and '+'
+const LICENSE_NAMES: [(&str, &str); 64] = [
+    ("mitlicense", "MIT"),
+    ("themitlicense", "MIT"),
+    ("mitx11", "MIT"),
+    ("expat", "MIT"),
+    ("apache2", "Apache-2.0"),
+    ("apache20", "Apache-2.0"),
+    ("apachelicense20", "Apache-2.0"),
+    ("apachelicenseversion20", "Apache-2.0"),
+    ("theapachelicenseversion20", "Apache-2.0"),
+    ("theapachesoftwarelicenseversion20", "Apache-2.0"),
+    ("apachesoftwarelicense20", "Apache-2.0"),
+    ("apachesoftwarelicense", "Apache-2.0"),
+    ("asl20", "Apache-2.0"),
+    ("apachelicense11", "Apache-1.1"),
+    ("bsd", "BSD-3-Clause"),
+    ("bsdlicense", "BSD-3-Clause"),
+    ("newbsd", "BSD-3-Clause"),
+    ("newbsdlicense", "BSD-3-Clause"),
+    ("modifiedbsd", "BSD-3-Clause"),
+    ("bsd3clause", "BSD-3-Clause"),
+    ("bsd3clauselicense", "BSD-3-Clause"),
+    ("3clausebsd", "BSD-3-Clause"),
+    ("revisedbsd", "BSD-3-Clause"),
+    ("simplifiedbsd", "BSD-2-Clause"),
+    ("freebsd", "BSD-2-Clause"),
+    ("bsd2clause", "BSD-2-Clause"),
+    ("2clausebsd", "BSD-2-Clause"),
+    ("isclicense", "ISC"),
+    ("gplv2", "GPL-2.0-only"),
+    ("gpl2", "GPL-2.0-only"),
+    ("gpl20", "GPL-2.0-only"),
+    ("gnugeneralpubliclicensev2", "GPL-2.0-only"),
+    ("gnugeneralpubliclicenseversion2", "GPL-2.0-only"),
+    ("gplv2+", "GPL-2.0-or-later"),
+    ("gplv3", "GPL-3.0-only"),
+    ("gpl3", "GPL-3.0-only"),
+    ("gpl30", "GPL-3.0-only"),
+    ("gnugeneralpubliclicensev3", "GPL-3.0-only"),
+    ("gnugeneralpubliclicenseversion3", "GPL-3.0-only"),
+    ("gplv3+", "GPL-3.0-or-later"),
+    ("lgplv21", "LGPL-2.1-only"),
+    ("lgpl21", "LGPL-2.1-only"),
+    ("gnulessergeneralpubliclicensev21", "LGPL-2.1-only"),
+    ("lgplv3", "LGPL-3.0-only"),
+    ("lgpl3", "LGPL-3.0-only"),
+    ("gnulessergeneralpubliclicensev3", "LGPL-3.0-only"),
+    ("lgplv3+", "LGPL-3.0-or-later"),
+    ("agplv3", "AGPL-3.0-only"),
+    ("agpl3", "AGPL-3.0-only"),
+    ("gnuafferogeneralpubliclicensev3", "AGPL-3.0-only"),
+    ("mpl20", "MPL-2.0"),
+    ("mozillapubliclicense20", "MPL-2.0"),
+    ("mozillapubliclicenseversion20", "MPL-2.0"),
+    ("epl10", "EPL-1.0"),
+    ("eclipsepubliclicense10", "EPL-1.0"),
+    ("epl20", "EPL-2.0"),
+    ("eclipsepubliclicense20", "EPL-2.0"),
+    ("cddl10", "CDDL-1.0"),
+    ("psf", "PSF-2.0"),
+    ("pythonsoftwarefoundationlicense", "PSF-2.0"),
+    ("boostsoftwarelicense10", "BSL-1.0"),
+    ("theunlicense", "Unlicense"),
+    ("cc0", "CC0-1.0"),
+    ("zliblicense", "Zlib"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LicenseExpr {
+    License {
+        id: String,
+        or_later: bool,
+        exception: Option<String>,
+    },
+    And(Vec<LicenseExpr>),
+    Or(Vec<LicenseExpr>),
+}
+
+fn name_key(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|x| x.is_ascii_alphanumeric() || *x == '+')
+        .collect()
+}
+
+// Map a license ID or free text license name to its SPDX ID, unknown names are
+// returned unchanged
+pub fn normalize_license(name: &str) -> String {
+    let name = name.trim();
+    if let Some(id) = SPDX_IDS.iter().find(|x| x.eq_ignore_ascii_case(name)) {
+        return id.to_string();
+    }
+    let key = name_key(name);
+    if let Some((_, id)) = LICENSE_NAMES.iter().find(|(x, _)| *x == key) {
+        return id.to_string();
+    }
+    name.to_string()
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Id(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let push_word = |word: &mut String, tokens: &mut Vec<Token>| {
+        if word.is_empty() {
+            return;
+        }
+        let token = match word.to_uppercase().as_str() {
+            "AND" => Token::And,
+            "OR" => Token::Or,
+            "WITH" => Token::With,
+            _ => Token::Id(word.clone()),
+        };
+        tokens.push(token);
+        word.clear();
+    };
+    for x in input.chars() {
+        match x {
+            '(' | ')' => {
+                push_word(&mut word, &mut tokens);
+                tokens.push(if x == '(' { Token::Open } else { Token::Close });
+            }
+            x if x.is_whitespace() => push_word(&mut word, &mut tokens),
+            _ => word.push(x),
+        }
+    }
+    push_word(&mut word, &mut tokens);
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    // OR has the lowest precedence, then AND, then WITH
+    fn parse_or(&mut self) -> Result<LicenseExpr, String> {
+        let mut operands = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            operands.push(self.parse_and()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpr::Or(operands)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<LicenseExpr, String> {
+        let mut operands = vec![self.parse_with()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            operands.push(self.parse_with()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpr::And(operands)
+        })
+    }
+
+    fn parse_with(&mut self) -> Result<LicenseExpr, String> {
+        let mut expression = self.parse_primary()?;
+        if self.peek() == Some(&Token::With) {
+            self.next();
+            let exception = match self.next() {
+                Some(Token::Id(exception)) => exception.clone(),
+                _ => return Err("expected license exception after WITH".to_string()),
+            };
+            match expression {
+                LicenseExpr::License {
+                    exception: ref mut current,
+                    ..
+                } => *current = Some(exception),
+                _ => return Err("WITH can only be used after a license".to_string()),
+            }
+        }
+        Ok(expression)
+    }
+
+    fn parse_primary(&mut self) -> Result<LicenseExpr, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Id(id)) => {
+                let (id, or_later) = match id.strip_suffix('+') {
+                    Some(id) => (id.to_string(), true),
+                    None => (id.clone(), false),
+                };
+                Ok(LicenseExpr::License {
+                    id: normalize_license(&id),
+                    or_later,
+                    exception: None,
+                })
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+impl LicenseExpr {
+    pub fn parse(input: &str) -> Result<LicenseExpr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input),
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+        if parser.position != parser.tokens.len() {
+            return Err(format!("unexpected {:?}", parser.tokens[parser.position]));
+        }
+        Ok(expression)
+    }
+
+    // Package metadata often has free text like "Apache License, Version 2.0" which is
+    // not a valid expression, such values are treated as a single license name
+    pub fn parse_lenient(input: &str) -> LicenseExpr {
+        let normalized = normalize_license(input);
+        if normalized != input.trim() {
+            return LicenseExpr::License {
+                id: normalized,
+                or_later: false,
+                exception: None,
+            };
+        }
+        match LicenseExpr::parse(input) {
+            Ok(expression) => expression,
+            Err(_) => LicenseExpr::License {
+                id: input.trim().to_string(),
+                or_later: false,
+                exception: None,
+            },
+        }
+    }
+
+    // Combine all licenses declared for a package, every one of them applies
+    pub fn all_of(licenses: &[String]) -> Option<LicenseExpr> {
+        let mut operands = licenses
+            .iter()
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| LicenseExpr::parse_lenient(x))
+            .collect::<Vec<LicenseExpr>>();
+        match operands.len() {
+            0 => None,
+            1 => Some(operands.remove(0)),
+            _ => Some(LicenseExpr::And(operands)),
+        }
+    }
+
+    pub fn license_ids(&self) -> Vec<String> {
+        match self {
+            LicenseExpr::License { id, .. } => vec![id.clone()],
+            LicenseExpr::And(operands) | LicenseExpr::Or(operands) => {
+                let mut ids = Vec::new();
+                for id in operands.iter().flat_map(|x| x.license_ids()) {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+                ids
+            }
+        }
+    }
+
+    // Check if the licensee can comply with the expression using only licenses accepted
+    // by `is_acceptable`: one option of an OR is enough, all parts of an AND are needed
+    pub fn is_satisfied_by<F: Fn(&str) -> bool>(&self, is_acceptable: &F) -> bool {
+        match self {
+            LicenseExpr::License { id, .. } => is_acceptable(id),
+            LicenseExpr::And(operands) => operands.iter().all(|x| x.is_satisfied_by(is_acceptable)),
+            LicenseExpr::Or(operands) => operands.iter().any(|x| x.is_satisfied_by(is_acceptable)),
+        }
+    }
+}
+
+impl fmt::Display for LicenseExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseExpr::License {
+                id,
+                or_later,
+                exception,
+            } => {
+                write!(f, "{}", id)?;
+                if *or_later {
+                    write!(f, "+")?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {}", exception)?;
+                }
+                Ok(())
+            }
+            LicenseExpr::And(operands) | LicenseExpr::Or(operands) => {
+                let operator = if matches!(self, LicenseExpr::And(_)) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                let parts = operands
+                    .iter()
+                    .map(|x| match x {
+                        LicenseExpr::License { .. } => x.to_string(),
+                        _ => format!("({})", x),
+                    })
+                    .collect::<Vec<String>>();
+                write!(f, "{}", parts.join(operator))
+            }
+        }
+    }
+}
+
+// A policy entry matches the license itself and its whole family, so "GPL" matches
+// "GPL-2.0-only" and "GPL-3.0-or-later" but not "LGPL-2.1-only"
+pub fn license_matches(pattern: &str, id: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    let id = id.to_lowercase();
+    id == pattern || id.starts_with(&format!("{}-", pattern)) || id == format!("{}+", pattern)
+}
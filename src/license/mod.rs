@@ -0,0 +1,4 @@
+pub mod expression;
+pub mod notices;
+pub mod policy;
+pub mod text;
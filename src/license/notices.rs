@@ -0,0 +1,293 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::Value;
+
+use super::{
+    expression::LicenseExpr,
+    text::{copyright_lines, is_license_file, license_text},
+};
+use crate::sbom::{cyclonedx::escape_xml, inventory_from_output, Component, License};
+
+const UNKNOWN_LICENSE: &str = "Unknown";
+
+pub struct Notice {
+    pub name: String,
+    pub version: String,
+    pub copyrights: Vec<String>,
+}
+
+pub struct NoticeGroup {
+    // SPDX expression all entries of the group are licensed under
+    pub license: String,
+    pub notices: Vec<Notice>,
+}
+
+fn component_license(component: &Component) -> String {
+    let licenses = component
+        .licenses
+        .iter()
+        .map(|x| match x {
+            License::Id(x) | License::Name(x) | License::Expression(x) => x.clone(),
+        })
+        .collect::<Vec<String>>();
+    match LicenseExpr::all_of(&licenses) {
+        Some(expression) => expression.to_string(),
+        None => UNKNOWN_LICENSE.to_string(),
+    }
+}
+
+fn file_copyrights(path: &Path) -> Vec<String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => copyright_lines(&text),
+        Err(_) => Vec::new(),
+    }
+}
+
+// cdxgen rarely knows the copyright holders, for npm packages the installed package in
+// node_modules next to the manifest usually has a LICENSE file with them
+fn installed_package_copyrights(component: &Component) -> Vec<String> {
+    if !component.purl.starts_with("pkg:npm/") {
+        return Vec::new();
+    }
+    let package_name = if component.group.is_empty() {
+        component.name.clone()
+    } else {
+        format!("{}/{}", component.group, component.name)
+    };
+    for manifest in component.manifests.iter() {
+        let package_dir = match Path::new(manifest).parent() {
+            Some(dir) => dir.join("node_modules").join(&package_name),
+            None => continue,
+        };
+        let entries = match std::fs::read_dir(&package_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            if is_license_file(&entry.path()) {
+                let copyrights = file_copyrights(&entry.path());
+                if !copyrights.is_empty() {
+                    return copyrights;
+                }
+            }
+        }
+    }
+    Vec::new()
+}
+
+// Group all third-party components (and license files / SPDX headers found in the source
+// tree under `root`) by their license
+pub fn collect_notices(
+    output_json: &Value,
+    root: &str,
+    license_files_key: &str,
+) -> Vec<NoticeGroup> {
+    let mut groups: BTreeMap<String, Vec<Notice>> = BTreeMap::new();
+    let inventory = inventory_from_output(output_json);
+    for component in inventory.components.iter() {
+        let mut copyrights = Vec::new();
+        if !component.copyright.is_empty() {
+            copyrights.push(component.copyright.clone());
+        }
+        for copyright in installed_package_copyrights(component) {
+            if !copyrights.contains(&copyright) {
+                copyrights.push(copyright);
+            }
+        }
+        let name = if component.group.is_empty() {
+            component.name.clone()
+        } else {
+            format!("{}/{}", component.group, component.name)
+        };
+        groups
+            .entry(component_license(component))
+            .or_default()
+            .push(Notice {
+                name,
+                version: component.version.clone(),
+                copyrights,
+            });
+    }
+    if let Some(source_files) = output_json[license_files_key].as_object() {
+        for (path, licenses) in source_files {
+            let licenses = licenses
+                .as_array()
+                .unwrap_or(&vec![])
+                .iter()
+                .filter_map(|x| x.as_str())
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>();
+            let license = match LicenseExpr::all_of(&licenses) {
+                Some(expression) => expression.to_string(),
+                None => UNKNOWN_LICENSE.to_string(),
+            };
+            groups.entry(license).or_default().push(Notice {
+                name: path.clone(),
+                version: String::new(),
+                copyrights: file_copyrights(&Path::new(root).join(path)),
+            });
+        }
+    }
+
+    let mut notice_groups = Vec::new();
+    let mut unknown = None;
+    for (license, mut notices) in groups {
+        notices.sort_by_key(|x| x.name.to_lowercase());
+        let group = NoticeGroup { license, notices };
+        if group.license == UNKNOWN_LICENSE {
+            unknown = Some(group);
+        } else {
+            notice_groups.push(group);
+        }
+    }
+    // components without a known license are listed last
+    notice_groups.extend(unknown);
+    notice_groups
+}
+
+// Every license referenced by the groups with its full text, licenses which are not
+// bundled with hela get a link to the SPDX license list instead
+fn license_texts(groups: &[NoticeGroup]) -> Vec<(String, String)> {
+    let mut ids = Vec::new();
+    for group in groups.iter().filter(|x| x.license != UNKNOWN_LICENSE) {
+        for id in LicenseExpr::parse_lenient(&group.license).license_ids() {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids.sort();
+    ids.into_iter()
+        .map(|id| {
+            let text = match license_text(&id) {
+                Some(text) => text.trim_matches('\n').trim_end().to_string(),
+                None => format!(
+                    "The license text is not bundled with Hela, see https://spdx.org/licenses/{}.html",
+                    id
+                ),
+            };
+            (id, text)
+        })
+        .collect()
+}
+
+fn package_label(notice: &Notice) -> String {
+    if notice.version.is_empty() {
+        return notice.name.clone();
+    }
+    format!("{} {}", notice.name, notice.version)
+}
+
+fn intro(project: &str) -> String {
+    format!(
+        "{} uses the third-party software listed below, grouped by license. Generated by Hela {}.",
+        project,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+pub fn to_markdown(groups: &[NoticeGroup], project: &str) -> String {
+    let mut lines = vec![
+        format!("# Third-party notices for {}", project),
+        String::new(),
+        intro(project),
+    ];
+    for group in groups.iter() {
+        lines.push(String::new());
+        lines.push(format!("## {}", group.license));
+        lines.push(String::new());
+        for notice in group.notices.iter() {
+            lines.push(format!("- **{}**", package_label(notice)));
+            for copyright in notice.copyrights.iter() {
+                lines.push(format!("  - {}", copyright));
+            }
+        }
+    }
+    lines.push(String::new());
+    lines.push("## License texts".to_string());
+    for (id, text) in license_texts(groups) {
+        lines.push(String::new());
+        lines.push(format!("### {}", id));
+        lines.push(String::new());
+        lines.push("```".to_string());
+        lines.push(text);
+        lines.push("```".to_string());
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+pub fn to_text(groups: &[NoticeGroup], project: &str) -> String {
+    let separator = "=".repeat(80);
+    let mut lines = vec![
+        format!("THIRD-PARTY NOTICES FOR {}", project.to_uppercase()),
+        String::new(),
+        intro(project),
+    ];
+    for group in groups.iter() {
+        lines.push(String::new());
+        lines.push(separator.clone());
+        lines.push(format!("Licensed under {}", group.license));
+        lines.push(separator.clone());
+        for notice in group.notices.iter() {
+            lines.push(String::new());
+            lines.push(package_label(notice));
+            for copyright in notice.copyrights.iter() {
+                lines.push(format!("    {}", copyright));
+            }
+        }
+    }
+    for (id, text) in license_texts(groups) {
+        lines.push(String::new());
+        lines.push(separator.clone());
+        lines.push(format!("License text: {}", id));
+        lines.push(separator.clone());
+        lines.push(String::new());
+        lines.push(text);
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+pub fn to_html(groups: &[NoticeGroup], project: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Third-party notices for {}</title>\n</head>\n<body>\n",
+        escape_xml(project)
+    ));
+    html.push_str(&format!(
+        "<h1>Third-party notices for {}</h1>\n<p>{}</p>\n",
+        escape_xml(project),
+        escape_xml(&intro(project))
+    ));
+    for group in groups.iter() {
+        html.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape_xml(&group.license)));
+        for notice in group.notices.iter() {
+            html.push_str(&format!(
+                "<li><strong>{}</strong>",
+                escape_xml(&package_label(notice))
+            ));
+            if !notice.copyrights.is_empty() {
+                html.push_str("\n<ul>\n");
+                for copyright in notice.copyrights.iter() {
+                    html.push_str(&format!("<li>{}</li>\n", escape_xml(copyright)));
+                }
+                html.push_str("</ul>\n");
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("<h2>License texts</h2>\n");
+    for (id, text) in license_texts(groups) {
+        html.push_str(&format!(
+            "<h3 id=\"{}\">{}</h3>\n<pre>{}</pre>\n",
+            escape_xml(&id),
+            escape_xml(&id),
+            escape_xml(&text)
+        ));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
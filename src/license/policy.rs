@@ -0,0 +1,284 @@
+use serde_yaml::Value;
+
+use super::expression::{license_matches, LicenseExpr};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    Permissive,
+    WeakCopyleft,
+    StrongCopyleft,
+    NetworkCopyleft,
+    Proprietary,
+    Unknown,
+}
+
+// Built-in classification, entries match the license family the same way policy entries
+// do (e.g. "GPL" covers "GPL-2.0-only" and "GPL-3.0-or-later"), the first match wins
+const CATEGORIES: [(&str, Category); 71] = [
+    ("AGPL", Category::NetworkCopyleft),
+    ("SSPL", Category::NetworkCopyleft),
+    ("OSL", Category::NetworkCopyleft),
+    ("CPAL", Category::NetworkCopyleft),
+    ("RPL", Category::NetworkCopyleft),
+    ("EUPL", Category::StrongCopyleft),
+    ("GPL", Category::StrongCopyleft),
+    ("CECILL", Category::StrongCopyleft),
+    ("CC-BY-SA", Category::StrongCopyleft),
+    ("ODbL", Category::StrongCopyleft),
+    ("Sleepycat", Category::StrongCopyleft),
+    ("QPL", Category::StrongCopyleft),
+    ("LGPL", Category::WeakCopyleft),
+    ("MPL", Category::WeakCopyleft),
+    ("EPL", Category::WeakCopyleft),
+    ("CDDL", Category::WeakCopyleft),
+    ("CPL", Category::WeakCopyleft),
+    ("MS-RL", Category::WeakCopyleft),
+    ("APSL", Category::WeakCopyleft),
+    ("Artistic", Category::WeakCopyleft),
+    ("OFL", Category::WeakCopyleft),
+    ("LGPLLR", Category::WeakCopyleft),
+    ("BUSL", Category::Proprietary),
+    ("Elastic", Category::Proprietary),
+    ("CC-BY-NC", Category::Proprietary),
+    ("CC-BY-ND", Category::Proprietary),
+    ("Commons-Clause", Category::Proprietary),
+    ("UNLICENSED", Category::Proprietary),
+    ("LicenseRef-proprietary", Category::Proprietary),
+    ("LicenseRef-commercial", Category::Proprietary),
+    ("JSON", Category::Proprietary),
+    ("MIT", Category::Permissive),
+    ("Apache", Category::Permissive),
+    ("BSD", Category::Permissive),
+    ("0BSD", Category::Permissive),
+    ("ISC", Category::Permissive),
+    ("Zlib", Category::Permissive),
+    ("Unlicense", Category::Permissive),
+    ("CC0", Category::Permissive),
+    ("CC-BY", Category::Permissive),
+    ("BSL", Category::Permissive),
+    ("PSF", Category::Permissive),
+    ("Python", Category::Permissive),
+    ("WTFPL", Category::Permissive),
+    ("X11", Category::Permissive),
+    ("UPL", Category::Permissive),
+    ("NCSA", Category::Permissive),
+    ("PostgreSQL", Category::Permissive),
+    ("BlueOak", Category::Permissive),
+    ("ICU", Category::Permissive),
+    ("Unicode", Category::Permissive),
+    ("W3C", Category::Permissive),
+    ("OpenSSL", Category::Permissive),
+    ("Ruby", Category::Permissive),
+    ("HPND", Category::Permissive),
+    ("ZPL", Category::Permissive),
+    ("AFL", Category::Permissive),
+    ("ECL", Category::Permissive),
+    ("MS-PL", Category::Permissive),
+    ("PHP", Category::Permissive),
+    ("IJG", Category::Permissive),
+    ("Beerware", Category::Permissive),
+    ("curl", Category::Permissive),
+    ("libpng", Category::Permissive),
+    ("NTP", Category::Permissive),
+    ("Vim", Category::Permissive),
+    ("bzip2", Category::Permissive),
+    ("FTL", Category::Permissive),
+    ("Info-ZIP", Category::Permissive),
+    ("TCL", Category::Permissive),
+    ("Xnet", Category::Permissive),
+];
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Permissive => "permissive",
+            Category::WeakCopyleft => "weak-copyleft",
+            Category::StrongCopyleft => "strong-copyleft",
+            Category::NetworkCopyleft => "network-copyleft",
+            Category::Proprietary => "proprietary",
+            Category::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Category> {
+        match name.trim().to_lowercase().as_str() {
+            "permissive" => Some(Category::Permissive),
+            "weak-copyleft" => Some(Category::WeakCopyleft),
+            "strong-copyleft" => Some(Category::StrongCopyleft),
+            "network-copyleft" => Some(Category::NetworkCopyleft),
+            "proprietary" => Some(Category::Proprietary),
+            "unknown" => Some(Category::Unknown),
+            _ => None,
+        }
+    }
+}
+
+pub fn category(id: &str) -> Category {
+    CATEGORIES
+        .iter()
+        .find(|(family, _)| license_matches(family, id))
+        .map(|(_, category)| *category)
+        .unwrap_or(Category::Unknown)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnknownAction {
+    Allow,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+pub struct Exception {
+    // package name or name@version
+    pub package: String,
+    // licenses the exception is limited to, empty means any license
+    pub licenses: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Allowed,
+    Excepted(String),
+    Blocked(Vec<String>),
+    Unknown,
+}
+
+// Parsed `license:` section of the policy file, entries of `contains` and `allowed` are
+// license IDs, license families (e.g. GPL) or category names (e.g. strong-copyleft)
+#[derive(Debug)]
+pub struct LicensePolicy {
+    pub blocked: Vec<String>,
+    pub allowed: Vec<String>,
+    pub exceptions: Vec<Exception>,
+    pub unknown: UnknownAction,
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|x| x.as_sequence())
+        .map(|x| {
+            x.iter()
+                .filter_map(|x| x.as_str())
+                .map(|x| x.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn entry_matches(entry: &str, id: &str) -> bool {
+    match Category::from_name(entry) {
+        Some(entry_category) => category(id) == entry_category,
+        None => license_matches(entry, id),
+    }
+}
+
+impl LicensePolicy {
+    pub fn from_yaml(policy: &Value) -> Result<LicensePolicy, String> {
+        let unknown = match policy.get("unknown").and_then(|x| x.as_str()) {
+            None | Some("warn") => UnknownAction::Warn,
+            Some("fail") => UnknownAction::Fail,
+            Some("allow") => UnknownAction::Allow,
+            Some(other) => {
+                return Err(format!(
+                    "invalid license.unknown value {}, use fail, warn or allow",
+                    other
+                ))
+            }
+        };
+        let mut exceptions = Vec::new();
+        for exception in policy
+            .get("exceptions")
+            .and_then(|x| x.as_sequence())
+            .unwrap_or(&vec![])
+        {
+            let package = match exception.get("package").and_then(|x| x.as_str()) {
+                Some(package) => package.to_string(),
+                None => return Err("license.exceptions entries need a package".to_string()),
+            };
+            exceptions.push(Exception {
+                package,
+                licenses: string_list(exception.get("licenses")),
+                reason: exception
+                    .get("reason")
+                    .and_then(|x| x.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            });
+        }
+        Ok(LicensePolicy {
+            blocked: string_list(policy.get("contains")),
+            allowed: string_list(policy.get("allowed")),
+            exceptions,
+            unknown,
+        })
+    }
+
+    fn is_blocked(&self, id: &str) -> bool {
+        self.blocked.iter().any(|x| entry_matches(x, id))
+    }
+
+    fn is_acceptable(&self, id: &str) -> bool {
+        if self.is_blocked(id) {
+            return false;
+        }
+        self.allowed.is_empty() || self.allowed.iter().any(|x| entry_matches(x, id))
+    }
+
+    fn exception_for(&self, package: &str, expression: Option<&LicenseExpr>) -> Option<&Exception> {
+        let name = package
+            .rsplit_once('@')
+            .map(|(name, _)| name)
+            .unwrap_or(package);
+        self.exceptions.iter().find(|exception| {
+            if exception.package != package && exception.package != name {
+                return false;
+            }
+            if exception.licenses.is_empty() {
+                return true;
+            }
+            // the exception only covers the listed licenses, anything else still applies
+            match expression {
+                Some(expression) => expression.is_satisfied_by(&|id: &str| {
+                    self.is_acceptable(id)
+                        || exception.licenses.iter().any(|x| license_matches(x, id))
+                }),
+                None => false,
+            }
+        })
+    }
+
+    // `package` is the name@version key LicenseTool stores, `expression` is None if
+    // no license was found for the package
+    pub fn evaluate(&self, package: &str, expression: Option<&LicenseExpr>) -> Verdict {
+        if let Some(exception) = self.exception_for(package, expression) {
+            return Verdict::Excepted(exception.reason.clone());
+        }
+        let expression = match expression {
+            Some(expression) => expression,
+            None => return Verdict::Unknown,
+        };
+        if expression.is_satisfied_by(&|id: &str| self.is_acceptable(id)) {
+            return Verdict::Allowed;
+        }
+        // licenses we can't classify are not blocked unless the policy says so
+        // explicitly, they are reported as unknown instead
+        let unknown_is_listed = |id: &str| {
+            self.blocked.iter().any(|x| entry_matches(x, id))
+                || self.allowed.iter().any(|x| entry_matches(x, id))
+        };
+        if expression.is_satisfied_by(&|id: &str| {
+            self.is_acceptable(id) || (category(id) == Category::Unknown && !unknown_is_listed(id))
+        }) {
+            return Verdict::Unknown;
+        }
+        Verdict::Blocked(
+            expression
+                .license_ids()
+                .into_iter()
+                .filter(|x| !self.is_acceptable(x))
+                .collect(),
+        )
+    }
+}
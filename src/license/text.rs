@@ -0,0 +1,314 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use super::expression::LicenseExpr;
+
+// License texts bundled with hela, file content is compared against these to find out
+// which license a LICENSE/COPYING file contains
+const LICENSE_TEXTS: [(&str, &str); 14] = [
+    ("0BSD", include_str!("texts/0BSD.txt")),
+    ("Apache-2.0", include_str!("texts/Apache-2.0.txt")),
+    ("BSD-2-Clause", include_str!("texts/BSD-2-Clause.txt")),
+    ("BSD-3-Clause", include_str!("texts/BSD-3-Clause.txt")),
+    ("CC0-1.0", include_str!("texts/CC0-1.0.txt")),
+    ("GPL-2.0-only", include_str!("texts/GPL-2.0.txt")),
+    ("GPL-3.0-only", include_str!("texts/GPL-3.0.txt")),
+    ("ISC", include_str!("texts/ISC.txt")),
+    ("LGPL-2.1-only", include_str!("texts/LGPL-2.1.txt")),
+    ("LGPL-3.0-only", include_str!("texts/LGPL-3.0.txt")),
+    ("MIT", include_str!("texts/MIT.txt")),
+    ("MPL-2.0", include_str!("texts/MPL-2.0.txt")),
+    ("Unlicense", include_str!("texts/Unlicense.txt")),
+    ("Zlib", include_str!("texts/Zlib.txt")),
+];
+
+const LICENSE_FILE_NAMES: [&str; 5] = ["license", "licence", "copying", "unlicense", "copyright"];
+const CODE_EXTENSIONS: [&str; 24] = [
+    "rs", "go", "js", "ts", "jsx", "tsx", "py", "java", "kt", "c", "h", "cpp", "cs", "rb", "php",
+    "swift", "json", "yaml", "yml", "toml", "xml", "html", "css", "sh",
+];
+
+// SPDX-License-Identifier headers are only looked for at the top of a file
+const HEADER_LINES: usize = 30;
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+// a template is a match if this share of its text is found in the file, or if the
+// file and the template are similar enough overall (e.g. Apache-2.0 without appendix)
+const CONTAINMENT_THRESHOLD: f64 = 0.9;
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+pub struct LicenseFinding {
+    // path relative to the scanned directory
+    pub path: String,
+    pub expression: String,
+    // "license-file" or "spdx-header"
+    pub source: &'static str,
+    pub confidence: f64,
+}
+
+struct Template {
+    id: &'static str,
+    shingles: HashSet<String>,
+}
+
+// Word trigrams of the text without punctuation, case and copyright lines, so formatting
+// and copyright holders don't affect the comparison
+fn shingles(text: &str) -> HashSet<String> {
+    let words = text
+        .lines()
+        .filter(|x| !x.trim_start().to_lowercase().starts_with("copyright"))
+        .flat_map(|x| {
+            x.split(|c: char| !c.is_alphanumeric())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_lowercase())
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<String>>();
+    words.windows(3).map(|x| x.join(" ")).collect()
+}
+
+fn templates() -> Vec<Template> {
+    LICENSE_TEXTS
+        .iter()
+        .map(|(id, text)| Template {
+            id,
+            shingles: shingles(text),
+        })
+        .collect()
+}
+
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> usize {
+    a.intersection(b).count()
+}
+
+// Find the licenses a license file contains, returns (SPDX expression, confidence)
+fn identify_license_text(text: &str, templates: &[Template]) -> Option<(String, f64)> {
+    let file_shingles = shingles(text);
+    if file_shingles.is_empty() {
+        return None;
+    }
+    let mut matched: Vec<(&Template, f64)> = templates
+        .iter()
+        .map(|x| {
+            let containment = overlap(&x.shingles, &file_shingles) as f64 / x.shingles.len() as f64;
+            (x, containment)
+        })
+        .filter(|(_, containment)| *containment >= CONTAINMENT_THRESHOLD)
+        .collect();
+    // a license which is mostly part of another matched license is not reported on its
+    // own, e.g. BSD-2-Clause inside a BSD-3-Clause text
+    let all_matched = matched.iter().map(|(x, _)| x.id).collect::<Vec<&str>>();
+    matched.retain(|(x, _)| {
+        !templates.iter().any(|other| {
+            other.id != x.id
+                && all_matched.contains(&other.id)
+                && other.shingles.len() > x.shingles.len()
+                && overlap(&x.shingles, &other.shingles) as f64 / x.shingles.len() as f64 >= 0.75
+        })
+    });
+    if matched.is_empty() {
+        let best = templates
+            .iter()
+            .map(|x| {
+                let similarity = 2.0 * overlap(&x.shingles, &file_shingles) as f64
+                    / (x.shingles.len() + file_shingles.len()) as f64;
+                (x, similarity)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if best.1 < SIMILARITY_THRESHOLD {
+            return None;
+        }
+        matched.push(best);
+    }
+    let confidence = matched.iter().map(|(_, x)| *x).fold(1.0, f64::min);
+    // the AGPL is the GPL-3.0 text with an additional section, tell them apart by title
+    let is_agpl = text.to_uppercase().contains("GNU AFFERO GENERAL PUBLIC");
+    let ids = matched
+        .iter()
+        .map(|(x, _)| {
+            if x.id == "GPL-3.0-only" && is_agpl {
+                "AGPL-3.0-only"
+            } else {
+                x.id
+            }
+        })
+        .collect::<Vec<&str>>();
+    Some((ids.join(" AND "), (confidence * 100.0).round() / 100.0))
+}
+
+// LICENSE, LICENSE-MIT, LICENSE.txt, COPYING.LESSER... but not source files like license.rs
+pub fn is_license_file(path: &Path) -> bool {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_lowercase(),
+        None => return false,
+    };
+    let extension = file_name.rsplit_once('.').map(|(_, x)| x).unwrap_or("");
+    if CODE_EXTENSIONS.contains(&extension) {
+        return false;
+    }
+    // REUSE compliant projects keep their license texts in LICENSES/<id>.txt
+    if let Some(parent) = path.parent().and_then(|x| x.file_name()) {
+        if parent == "LICENSES" {
+            return true;
+        }
+    }
+    LICENSE_FILE_NAMES
+        .iter()
+        .any(|x| match file_name.strip_prefix(x) {
+            Some(rest) => rest.is_empty() || rest.starts_with(['.', '-', '_']),
+            None => false,
+        })
+}
+
+fn spdx_header(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    for line in BufReader::new(file).lines().take(HEADER_LINES) {
+        let line = line.ok()?;
+        if let Some((_, expression)) = line.split_once("SPDX-License-Identifier:") {
+            // strip comment terminators like */ or -->
+            let expression = expression
+                .trim()
+                .trim_end_matches("*/")
+                .trim_end_matches("-->")
+                .trim_end_matches("#}")
+                .trim();
+            if expression.is_empty() {
+                return None;
+            }
+            return Some(LicenseExpr::parse_lenient(expression).to_string());
+        }
+    }
+    None
+}
+
+fn walk(dir: &Path, ignore_dirs: &[&str], files: &mut Vec<std::path::PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_type.is_dir() {
+            if file_name != ".git" && !ignore_dirs.contains(&file_name.as_str()) {
+                walk(&path, ignore_dirs, files);
+            }
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+// Look for license texts (LICENSE, COPYING...) and SPDX-License-Identifier headers in the
+// whole tree, this finds vendored or copied code that package manifests don't know about
+pub fn scan_tree(root: &str, ignore_dirs: &[&str]) -> Vec<LicenseFinding> {
+    let templates = templates();
+    let root_path = Path::new(root);
+    let mut files = Vec::new();
+    walk(root_path, ignore_dirs, &mut files);
+    files.sort();
+
+    let mut findings = Vec::new();
+    for file in files.iter() {
+        let size = std::fs::metadata(file).map(|x| x.len()).unwrap_or(0);
+        if size == 0 || size > MAX_FILE_SIZE {
+            continue;
+        }
+        let relative_path = file
+            .strip_prefix(root_path)
+            .unwrap_or(file)
+            .to_string_lossy()
+            .to_string();
+        if is_license_file(file) {
+            let text = match std::fs::read_to_string(file) {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+            if let Some((expression, confidence)) = identify_license_text(&text, &templates) {
+                findings.push(LicenseFinding {
+                    path: relative_path,
+                    expression,
+                    source: "license-file",
+                    confidence,
+                });
+            }
+            continue;
+        }
+        if let Some(expression) = spdx_header(file) {
+            findings.push(LicenseFinding {
+                path: relative_path,
+                expression,
+                source: "spdx-header",
+                confidence: 1.0,
+            });
+        }
+    }
+    findings
+}
+
+// Full text of a bundled license, GPL-2.0-only, GPL-2.0-or-later and GPL-2.0+ share a text
+pub fn license_text(id: &str) -> Option<&'static str> {
+    let base = id
+        .trim_end_matches('+')
+        .trim_end_matches("-only")
+        .trim_end_matches("-or-later");
+    LICENSE_TEXTS
+        .iter()
+        .find(|(x, _)| x.trim_end_matches("-only").eq_ignore_ascii_case(base))
+        .map(|(_, text)| *text)
+}
+
+// Copyright statements of a license file or source header, lines which are part of the
+// license texts themselves (e.g. "Copyright (C) 2007 Free Software Foundation") are skipped
+pub fn copyright_lines(text: &str) -> Vec<String> {
+    let template_lines = LICENSE_TEXTS
+        .iter()
+        .flat_map(|(_, x)| x.lines())
+        .map(|x| x.trim())
+        .collect::<HashSet<&str>>();
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        // strip comment markers of source file headers
+        let line = line
+            .trim()
+            .trim_start_matches(['/', '*', '#', '-', ';', '!'])
+            .trim();
+        let lowercase = line.to_lowercase();
+        let is_copyright = lowercase.starts_with("copyright")
+            || lowercase.starts_with("(c)")
+            || line.starts_with('\u{a9}');
+        // placeholders of license templates like "Copyright (c) <year> <owner>"
+        let is_placeholder = [
+            "<year",
+            "[year",
+            "<copyright",
+            "[fullname",
+            "<owner",
+            "<name",
+        ]
+        .iter()
+        .any(|x| lowercase.contains(x));
+        if !is_copyright || is_placeholder || template_lines.contains(line) {
+            continue;
+        }
+        // "Copyright notice" and similar sentences don't name a copyright holder
+        if !line.chars().any(|x| x.is_ascii_digit())
+            && !lowercase.contains("(c)")
+            && !line.contains('\u{a9}')
+        {
+            continue;
+        }
+        if !lines.contains(&line.to_string()) {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
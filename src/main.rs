@@ -1,15 +1,32 @@
+mod commands;
+mod license;
+mod sbom;
 mod scans;
+mod secrets;
+mod store;
 mod utils;
 use crate::scans::tools::{
-    license_tool::LicenseTool, sast_tool::SastTool, sca_tool::ScaTool, secret_tool::SecretTool,
+    license_tool::LicenseTool,
+    sast_tool::SastTool,
+    sca_tool::ScaTool,
+    secret_tool::{SecretEngineMode, SecretOptions, SecretTool},
 };
 use argparse::{ArgumentParser, Store, StoreTrue};
-use scans::scanner::ScanRunner;
-use utils::pipeline;
+use scans::{scanner::ScanRunner, suppressions::Suppressions};
+use secrets::mask_output;
+use std::io::{stderr, stdout};
+use store::StoreConfig;
+use utils::{
+    baseline::Baseline,
+    common::{current_branch, is_ci, prepare_checkout, print_error},
+    diff::{filter_output_file, DiffScope},
+    git_auth::{self, AuthOptions},
+    pipeline::{self, RecordPolicy},
+};
 
 async fn execute_scan(
     scan_type: &str,
-    mongo_uri: &str,
+    suppressions: &Suppressions,
     path: &str,
     base_branch: Option<&str>,
     pr_branch: Option<&str>,
@@ -18,18 +35,19 @@ async fn execute_scan(
     build_args: String,
     manifests: String,
     rule_path: String,
+    secret_options: SecretOptions,
     verbose: bool,
 ) {
     let scanner = ScanRunner::new(
         SastTool::new(),
         ScaTool::new(),
-        SecretTool::new(),
+        SecretTool::with_options(secret_options),
         LicenseTool::new(),
     );
 
     scanner
         .execute_scan(
-            mongo_uri,
+            suppressions,
             scan_type,
             path,
             base_branch,
@@ -46,6 +64,35 @@ async fn execute_scan(
 
 #[tokio::main]
 async fn main() {
+    // subcommands get their own argument parser, everything else is a scan
+    let mut args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(|x| x.as_str()) {
+        Some("sbom") => {
+            commands::sbom::run(args[1..].to_vec()).await;
+            return;
+        }
+        Some("notices") => {
+            commands::notices::run(args[1..].to_vec()).await;
+            return;
+        }
+        Some("allowlist") => {
+            commands::allowlist::run(args[1..].to_vec()).await;
+            return;
+        }
+        Some("baseline") => {
+            commands::baseline::run(args[1..].to_vec()).await;
+            return;
+        }
+        Some("triage") => {
+            commands::triage::run(args[1..].to_vec()).await;
+            return;
+        }
+        Some("scan") => {
+            args.remove(1);
+        }
+        _ => {}
+    }
+
     // Parse command-line arguments
     let mut no_install = false;
     let mut root_only = false;
@@ -67,8 +114,27 @@ async fn main() {
     let mut manifests = String::new();
     let mut json = false;
     let mut slack_url = String::new();
-    let mut mongo_uri = String::new();
+    let mut store_uri = String::new();
+    let mut db_name = String::new();
+    let mut db_collections = String::new();
     let mut job_id = String::new();
+    let mut sbom_path = String::new();
+    let mut secret_engine = "auto".to_string();
+    let mut native_secret_rules = String::new();
+    let mut secret_rules = String::new();
+    let mut secret_history = false;
+    let mut secret_commit_range = String::new();
+    let mut verify_secrets = false;
+    let mut secret_verifiers = String::new();
+    let mut show_secrets = false;
+    let mut baseline_path = String::new();
+    let mut update_baseline = false;
+    let mut diff_base = String::new();
+    let mut diff_head = String::new();
+    let mut diff_context: usize = 0;
+    let mut commit_id = String::new();
+    let mut auth_options = AuthOptions::default();
+    let mut baseline_branches = "main,master".to_string();
 
     {
         let mut ap = ArgumentParser::new();
@@ -90,11 +156,32 @@ async fn main() {
             Store,
             "Specify the base branch to scan or compare",
         );
+        ap.refer(&mut commit_id).add_option(
+            &["-i", "--commit", "--commit-id"],
+            Store,
+            "Scan the project at this commit (or tag), a local project is also scanned at --branch",
+        );
         ap.refer(&mut pr_branch).add_option(
             &["--pr-branch"],
             Store,
             "Specify the PR branch to compare with the base branch (optional)",
         );
+        git_auth::add_options(&mut ap, &mut auth_options);
+        ap.refer(&mut diff_base).add_option(
+            &["--base"],
+            Store,
+            "Only report findings in the changes since this ref (branch, tag or commit), the full tree is still scanned",
+        );
+        ap.refer(&mut diff_head).add_option(
+            &["--head"],
+            Store,
+            "Ref to scan with --base (default: the working tree of a local project, HEAD of a remote one)",
+        );
+        ap.refer(&mut diff_context).add_option(
+            &["--diff-context"],
+            Store,
+            "Also report SAST findings this many lines around a change of --base or --pr-branch (default: 0)",
+        );
         ap.refer(&mut is_sast)
             .add_option(&["-s", "--sast"], StoreTrue, "Run SAST scan");
         ap.refer(&mut defectdojo_url).add_option(
@@ -164,22 +251,267 @@ async fn main() {
         ap.refer(&mut job_id).add_option(
             &["-w", "--job-id"],
             Store,
-            "Pass the job id to store scan results in mongo db",
+            "Pass the job id to store scan results in the finding store",
+        );
+        ap.refer(&mut store_uri).add_option(
+            &["-o", "--mongo-uri", "--store-uri"],
+            Store,
+            "Pass the uri of the finding store for known findings, allowlists and scan results (mongodb:// or sqlite://<path>)",
+        );
+        ap.refer(&mut db_name).add_option(
+            &["--db-name"],
+            Store,
+            "Name of the database in the finding store (default: code-security-open-source)",
+        );
+        ap.refer(&mut db_collections).add_option(
+            &["--db-collections"],
+            Store,
+            "Override collection names, e.g. hashes=hela_hashes,jobs=hela_jobs (hashes, allowlist, secrets, jobs, triage)",
+        );
+        ap.refer(&mut sbom_path).add_option(
+            &["--sbom"],
+            Store,
+            "Scan an existing CycloneDX or SPDX SBOM instead of source code (SCA and License Compliance only)",
+        );
+        ap.refer(&mut secret_engine).add_option(
+            &["--secret-engine"],
+            Store,
+            "Secret detection engine to use, supports auto, native, trufflehog and both (default: auto)",
+        );
+        ap.refer(&mut native_secret_rules).add_option(
+            &["--native-secret-rules"],
+            Store,
+            "Pass a YAML file with additional rules for the native secret engine",
+        );
+        ap.refer(&mut secret_rules).add_option(
+            &["--secret-rules"],
+            Store,
+            "Pass the secret rules file with detector, value and path filters (Local Path or HTTP URL)",
+        );
+        ap.refer(&mut secret_history).add_option(
+            &["--secret-history"],
+            StoreTrue,
+            "Scan the whole git history for secrets instead of the working tree",
+        );
+        ap.refer(&mut secret_commit_range).add_option(
+            &["--secret-commit-range"],
+            Store,
+            "Scan only the commits of a range for secrets, <from>..<to> or <from> for <from>..HEAD (implies --secret-history)",
+        );
+        ap.refer(&mut verify_secrets).add_option(
+            &["--verify-secrets"],
+            StoreTrue,
+            "Check whether found secrets are live by calling the verifier of their detector",
+        );
+        ap.refer(&mut secret_verifiers).add_option(
+            &["--secret-verifiers"],
+            Store,
+            "Pass a YAML file with additional HTTP secret verifiers (Local Path or HTTP URL, implies --verify-secrets)",
+        );
+        ap.refer(&mut show_secrets).add_option(
+            &["--show-secrets"],
+            StoreTrue,
+            "Show secret values unmasked in the console output, local runs only",
+        );
+        ap.refer(&mut baseline_path).add_option(
+            &["--baseline"],
+            Store,
+            "Pass a baseline file created by `hela baseline create`, only findings which are not in it are reported",
+        );
+        ap.refer(&mut update_baseline).add_option(
+            &["--update-baseline"],
+            StoreTrue,
+            "Record the new findings of this run in the finding store as known, even if the pipeline failed or the branch isn't a baseline branch",
         );
-        ap.refer(&mut mongo_uri).add_option(
-            &["-o", "--mongo-uri"],
+        ap.refer(&mut baseline_branches).add_option(
+            &["--baseline-branches"],
             Store,
-            "Pass the mongo uri to store scan results",
+            "Comma separated branches whose passing runs record their new findings as known (default: main,master)",
         );
-        ap.parse_args_or_exit();
+        if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+            std::process::exit(code);
+        }
     }
 
     if verbose {
         println!("[+] Verbose mode enabled!");
     }
-    if mongo_uri != "" {
-        println!("[+] Found DbConnection, we will be using it for filtering out the results");
+    let store = if store_uri.is_empty() {
+        None
+    } else {
+        let store = match StoreConfig::new(&db_name, &db_collections) {
+            Ok(config) => store::open(&store_uri, &config).await,
+            Err(e) => Err(e),
+        };
+        match store {
+            Ok(store) => {
+                println!(
+                    "[+] Found DbConnection, we will be using it for filtering out the results"
+                );
+                Some(store)
+            }
+            Err(e) => {
+                print_error(
+                    &format!("Error: Unable to open the finding store: {}", e),
+                    1,
+                );
+                return;
+            }
+        }
+    };
+
+    let secret_options = match SecretEngineMode::from_name(&secret_engine) {
+        Some(engine) => SecretOptions {
+            engine,
+            native_rules: native_secret_rules,
+            rules: secret_rules,
+            history: secret_history,
+            commit_range: secret_commit_range,
+            verify: verify_secrets,
+            verifiers: secret_verifiers,
+        },
+        None => {
+            print_error(
+                &format!(
+                    "Error: Unsupported secret engine {}, use auto, native, trufflehog or both",
+                    secret_engine
+                ),
+                1,
+            );
+            return;
+        }
+    };
+
+    // unmasked secrets would end up in CI logs
+    if show_secrets && is_ci() {
+        print_error(
+            "Warning: --show-secrets is ignored in CI environments, secrets stay masked",
+            101,
+        );
+        show_secrets = false;
+    }
+
+    // credentials are applied to the git commands, the code path keeps none
+    match git_auth::setup(&path, &auth_options).await {
+        Ok(clean_path) => path = clean_path,
+        Err(e) => {
+            print_error(
+                &format!("Error: Unable to set up git authentication: {}", e),
+                1,
+            );
+            return;
+        }
+    }
+    let history = is_secret && (secret_options.history || !secret_options.commit_range.is_empty());
+    if auth_options.depth > 0 && history {
+        print_error(
+            &format!(
+                "Warning: --clone-depth limits the secret history scan to the last {} commits",
+                auth_options.depth
+            ),
+            101,
+        );
+    }
+
+    // a diff scan checks out the whole head tree up front, the scans find it in /tmp/app
+    let diff_scope = if diff_base.is_empty() {
+        if !diff_head.is_empty() {
+            print_error("Error: --head needs a --base to compare with", 1);
+        }
+        None
+    } else {
+        if !pr_branch.is_empty() {
+            print_error(
+                "Error: --base and --head can't be combined with --pr-branch",
+                1,
+            );
+        }
+        let _ = std::fs::remove_dir_all("/tmp/app");
+        match DiffScope::prepare(&path, "/tmp/app", &diff_base, &diff_head) {
+            Ok(scope) => {
+                println!(
+                    "[+] Reporting findings in {} files changed since {}",
+                    scope.changed_files(),
+                    diff_base
+                );
+                Some(scope)
+            }
+            Err(e) => {
+                print_error(&format!("Error: Unable to prepare the diff scan: {}", e), 1);
+                return;
+            }
+        }
+    };
+
+    // a ref of a local project (or a commit of a remote one) is checked out up front, the
+    // scans find it in /tmp/app. Its export has no git history, so history scans of a local
+    // project scan its working tree and .git.
+    let local = !path.starts_with("http");
+    let reference = if !commit_id.is_empty() {
+        if !pr_branch.is_empty() || diff_scope.is_some() {
+            print_error(
+                "Error: --commit can't be combined with --pr-branch or --base, use --head to compare a commit",
+                1,
+            );
+        }
+        if local && history {
+            print_error(
+                "Error: A local project scanned at a commit has no git history, end --secret-commit-range at the commit instead",
+                1,
+            );
+        }
+        commit_id.clone()
+    } else if local && !history && pr_branch.is_empty() && diff_scope.is_none() {
+        base_branch.clone()
+    } else {
+        String::new()
+    };
+    if !sbom_path.is_empty() {
+        let ignored = match (base_branch.is_empty(), commit_id.is_empty()) {
+            (false, false) => "--branch and --commit are",
+            (false, true) => "--branch is",
+            (true, false) => "--commit is",
+            (true, true) => "",
+        };
+        if !ignored.is_empty() {
+            print_error(
+                &format!(
+                    "Warning: {} ignored, an --sbom scan doesn't check out the project",
+                    ignored
+                ),
+                101,
+            );
+        }
+    } else if !base_branch.is_empty() && pr_branch.is_empty() {
+        let reason = if !commit_id.is_empty() {
+            Some("the project is scanned at --commit")
+        } else if diff_scope.is_some() {
+            Some("use --head to choose the scanned ref of a --base scan")
+        } else if local && history {
+            Some("a local history scan covers all branches, use --secret-commit-range to limit it")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            print_error(&format!("Warning: --branch is ignored, {}", reason), 101);
+        }
+    }
+    if sbom_path.is_empty() && pr_branch.is_empty() && diff_scope.is_none() {
+        // ignored files aren't scanned
+        if let Err(e) = prepare_checkout(&path, "/tmp/app", &reference, history) {
+            print_error(&format!("Error: {}", e), 1);
+            return;
+        }
     }
+    // the tools would check out the branch again
+    let scan_branch = if reference.is_empty() {
+        base_branch.clone()
+    } else {
+        String::new()
+    };
+
+    // allowlists are fetched once and shared by all scans of this run
+    let suppressions = Suppressions::new(store.clone());
 
     let pr_branch_option = if pr_branch.is_empty() {
         None
@@ -187,84 +519,206 @@ async fn main() {
         Some(pr_branch.as_str())
     };
 
-    if is_sast {
-        execute_scan(
-            "sast",
-            &mongo_uri,
-            &path,
-            Some(&base_branch),
-            pr_branch_option,
-            no_install,
-            root_only,
-            build_args.clone(),
-            manifests.clone(),
-            rule_path.clone(),
-            verbose,
-        )
-        .await;
-    }
+    if !sbom_path.is_empty() {
+        // an SBOM only has components, so without any scan flags run everything that applies
+        if !is_sast && !is_sca && !is_secret && !is_license_compliance {
+            is_sca = true;
+            is_license_compliance = true;
+        }
+        if path.is_empty() {
+            path = sbom_path.clone();
+        }
+        let scanner = ScanRunner::new(
+            SastTool::new(),
+            ScaTool::new(),
+            SecretTool::new(),
+            LicenseTool::new(),
+        );
+        for (enabled, scan_type) in [
+            (is_sast, "sast"),
+            (is_sca, "sca"),
+            (is_secret, "secret"),
+            (is_license_compliance, "license-compliance"),
+        ] {
+            if enabled {
+                scanner
+                    .execute_sbom_scan(scan_type, &sbom_path, verbose)
+                    .await;
+            }
+        }
+        // SAST and secret results can't exist for a SBOM
+        is_sast = false;
+        is_secret = false;
+    } else {
+        if is_sast {
+            execute_scan(
+                "sast",
+                &suppressions,
+                &path,
+                Some(&scan_branch),
+                pr_branch_option,
+                no_install,
+                root_only,
+                build_args.clone(),
+                manifests.clone(),
+                rule_path.clone(),
+                secret_options.clone(),
+                verbose,
+            )
+            .await;
+        }
 
-    if is_sca {
-        execute_scan(
-            "sca",
-            &mongo_uri,
-            &path,
-            Some(&base_branch),
-            pr_branch_option,
-            no_install,
-            root_only,
-            build_args.clone(),
-            manifests.clone(),
-            rule_path.clone(),
-            verbose,
-        )
-        .await;
-    }
+        if is_sca {
+            execute_scan(
+                "sca",
+                &suppressions,
+                &path,
+                Some(&scan_branch),
+                pr_branch_option,
+                no_install,
+                root_only,
+                build_args.clone(),
+                manifests.clone(),
+                rule_path.clone(),
+                secret_options.clone(),
+                verbose,
+            )
+            .await;
+        }
 
-    if is_secret {
-        execute_scan(
-            "secret",
-            &mongo_uri,
-            &path,
-            Some(&base_branch),
-            pr_branch_option,
-            no_install,
-            root_only,
-            build_args.clone(),
-            manifests.clone(),
-            rule_path.clone(),
-            verbose,
-        )
-        .await;
-    }
+        if is_secret {
+            execute_scan(
+                "secret",
+                &suppressions,
+                &path,
+                Some(&scan_branch),
+                pr_branch_option,
+                no_install,
+                root_only,
+                build_args.clone(),
+                manifests.clone(),
+                rule_path.clone(),
+                secret_options.clone(),
+                verbose,
+            )
+            .await;
+        }
 
-    if is_license_compliance {
-        execute_scan(
-            "license-compliance",
-            &mongo_uri,
-            &path,
-            Some(&base_branch),
-            pr_branch_option,
-            no_install,
-            root_only,
-            build_args.clone(),
-            manifests.clone(),
-            rule_path.clone(),
-            verbose,
-        )
-        .await;
+        if is_license_compliance {
+            execute_scan(
+                "license-compliance",
+                &suppressions,
+                &path,
+                Some(&scan_branch),
+                pr_branch_option,
+                no_install,
+                root_only,
+                build_args.clone(),
+                manifests.clone(),
+                rule_path.clone(),
+                secret_options.clone(),
+                verbose,
+            )
+            .await;
+        }
     }
 
     if !is_sast && !is_sca && !is_secret && !is_license_compliance {
         println!("Invalid command. Available commands: sast, sca, secret, license-compliance");
     }
 
+    if let Some(scope) = diff_scope {
+        let scope = scope.with_context(diff_context);
+        let removed = filter_output_file("/tmp/output.json", |output| {
+            scope.filter_output(output, "/tmp/app")
+        });
+        if removed > 0 {
+            println!(
+                "[+] {} findings outside the changes since {} are not reported",
+                removed, diff_base
+            );
+        }
+    } else if is_sast && !pr_branch.is_empty() && path.starts_with("http") {
+        // a PR checkout only keeps the touched files (as they are on the PR branch), SAST
+        // findings are further limited to the lines the PR changes
+        let base = if base_branch.is_empty() {
+            "HEAD"
+        } else {
+            base_branch.as_str()
+        };
+        match DiffScope::from_refs("/tmp/app", base, &format!("origin/{}", pr_branch)) {
+            Ok(scope) => {
+                let scope = scope.with_context(diff_context);
+                let removed = filter_output_file("/tmp/output.json", |output| {
+                    scope.filter_sast(output, "/tmp/app")
+                });
+                if removed > 0 {
+                    println!(
+                        "[+] {} SAST findings outside the changed lines of {} are not reported",
+                        removed, pr_branch
+                    );
+                }
+            }
+            Err(e) => print_error(
+                &format!(
+                    "Warning: Unable to diff {}, SAST findings aren't limited to changed lines: {}",
+                    pr_branch, e
+                ),
+                101,
+            ),
+        }
+    }
+
     if json {
         if std::path::Path::new("/tmp/output.json").exists() {
             let output = std::fs::read_to_string("/tmp/output.json").unwrap();
-            println!("{}", output);
+            if show_secrets {
+                println!("{}", output);
+            } else {
+                let mut output_json =
+                    serde_json::from_str::<serde_json::Value>(&output).unwrap_or_default();
+                mask_output(&mut output_json);
+                println!("{}", serde_json::to_string_pretty(&output_json).unwrap());
+            }
         }
     } else {
+        // a baseline committed to the scanned repository is found in its checkout
+        let baseline = if baseline_path.is_empty() {
+            None
+        } else {
+            let location = if std::path::Path::new(&baseline_path).exists() {
+                baseline_path.clone()
+            } else {
+                format!("/tmp/app/{}", baseline_path.trim_start_matches("./"))
+            };
+            match Baseline::load(&location) {
+                Ok(baseline) => Some(baseline),
+                Err(e) => {
+                    print_error(
+                        &format!("Error: Unable to read baseline {}: {}", baseline_path, e),
+                        1,
+                    );
+                    return;
+                }
+            }
+        };
+        // a PR run is never a baseline run, without --branch the checkout tells the branch
+        let branch = if !pr_branch.is_empty() {
+            pr_branch.clone()
+        } else if !base_branch.is_empty() {
+            base_branch.clone()
+        } else {
+            current_branch("/tmp/app").unwrap_or_default()
+        };
+        let record_policy = RecordPolicy {
+            force: update_baseline,
+            branches: baseline_branches
+                .split(',')
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty())
+                .collect(),
+            branch,
+        };
         pipeline::pipeline_failure(
             path.clone(),
             is_sast,
@@ -274,11 +728,14 @@ async fn main() {
             policy_url,
             slack_url,
             job_id,
-            mongo_uri,
+            store,
             defectdojo_url,
             defectdojo_token,
             product_name,
             engagement_name,
+            show_secrets,
+            baseline,
+            record_policy,
         )
         .await;
     }
@@ -0,0 +1,342 @@
+use chrono::Utc;
+use mongodb::bson::uuid;
+use serde_json::{json, Value};
+
+use super::{Component, Inventory, License};
+
+pub const SPEC_VERSION: &str = "1.5";
+
+fn root_ref(project: &str) -> String {
+    format!("hela:{}", project)
+}
+
+fn license_json(license: &License) -> Value {
+    match license {
+        License::Id(id) => json!({ "license": { "id": id } }),
+        License::Name(name) => json!({ "license": { "name": name } }),
+        License::Expression(expression) => json!({ "expression": expression }),
+    }
+}
+
+// CycloneDX only allows a single expression or a list of licenses, never both
+fn component_licenses(component: &Component) -> Vec<&License> {
+    match component
+        .licenses
+        .iter()
+        .find(|x| matches!(x, License::Expression(_)))
+    {
+        Some(expression) => vec![expression],
+        None => component.licenses.iter().collect(),
+    }
+}
+
+fn component_json(component: &Component) -> Value {
+    let mut value = json!({
+        "type": component.component_type,
+        "bom-ref": component.bom_ref,
+        "name": component.name,
+    });
+    if !component.group.is_empty() {
+        value["group"] = json!(component.group);
+    }
+    if !component.version.is_empty() {
+        value["version"] = json!(component.version);
+    }
+    if !component.purl.is_empty() {
+        value["purl"] = json!(component.purl);
+    }
+    if !component.hashes.is_empty() {
+        value["hashes"] = Value::Array(
+            component
+                .hashes
+                .iter()
+                .map(|x| json!({ "alg": x.alg, "content": x.content }))
+                .collect(),
+        );
+    }
+    let licenses = component_licenses(component);
+    if !licenses.is_empty() {
+        value["licenses"] = Value::Array(licenses.into_iter().map(license_json).collect());
+    }
+    value["properties"] = Value::Array(
+        component
+            .manifests
+            .iter()
+            .map(|x| json!({ "name": "hela:manifest", "value": x }))
+            .collect(),
+    );
+    value
+}
+
+fn cwe_number(cwe: &str) -> Option<u64> {
+    cwe.trim_start_matches("CWE-").parse::<u64>().ok()
+}
+
+pub fn to_json(inventory: &Inventory, project: &str) -> Value {
+    let mut dependencies = vec![json!({
+        "ref": root_ref(project),
+        "dependsOn": inventory.root_dependencies,
+    })];
+    for dependency in inventory.dependencies.iter() {
+        dependencies.push(json!({
+            "ref": dependency.bom_ref,
+            "dependsOn": dependency.depends_on,
+        }));
+    }
+    let mut vulnerabilities = Vec::new();
+    for vuln in inventory.vulnerabilities.iter() {
+        let mut vulnerability = json!({
+            "bom-ref": vuln.id,
+            "id": vuln.id,
+            "source": {
+                "name": "OSV",
+                "url": format!("https://osv.dev/vulnerability/{}", vuln.id),
+            },
+            "ratings": [{ "severity": cyclonedx_severity(&vuln.severity), "method": "other" }],
+            "description": vuln.summary,
+            "detail": vuln.details,
+            "analysis": { "state": "in_triage" },
+            "affects": vuln.affects.iter().map(|x| json!({ "ref": x })).collect::<Vec<Value>>(),
+        });
+        let cwes = vuln
+            .cwes
+            .iter()
+            .filter_map(|x| cwe_number(x))
+            .collect::<Vec<u64>>();
+        if !cwes.is_empty() {
+            vulnerability["cwes"] = json!(cwes);
+        }
+        if !vuln.aliases.is_empty() {
+            vulnerability["references"] = Value::Array(
+                vuln.aliases
+                    .iter()
+                    .map(|x| json!({ "id": x, "source": { "name": "OSV" } }))
+                    .collect(),
+            );
+        }
+        vulnerabilities.push(vulnerability);
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": SPEC_VERSION,
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new()),
+        "version": 1,
+        "metadata": {
+            "timestamp": Utc::now().to_rfc3339(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "Hela",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]
+            },
+            "component": {
+                "type": "application",
+                "bom-ref": root_ref(project),
+                "name": project,
+            }
+        },
+        "components": inventory.components.iter().map(component_json).collect::<Vec<Value>>(),
+        "dependencies": dependencies,
+        "vulnerabilities": vulnerabilities,
+    })
+}
+
+fn cyclonedx_severity(severity: &str) -> &str {
+    match severity {
+        "critical" | "high" | "medium" | "low" | "info" | "none" => severity,
+        _ => "unknown",
+    }
+}
+
+pub fn escape_xml(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn push_element(xml: &mut String, indent: usize, name: &str, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    xml.push_str(&format!(
+        "{}<{}>{}</{}>\n",
+        "  ".repeat(indent),
+        name,
+        escape_xml(value),
+        name
+    ));
+}
+
+pub fn to_xml(inventory: &Inventory, project: &str) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<bom xmlns=\"http://cyclonedx.org/schema/bom/{}\" serialNumber=\"urn:uuid:{}\" version=\"1\">\n",
+        SPEC_VERSION,
+        uuid::Uuid::new()
+    ));
+    xml.push_str("  <metadata>\n");
+    push_element(&mut xml, 2, "timestamp", &Utc::now().to_rfc3339());
+    xml.push_str("    <tools>\n      <components>\n        <component type=\"application\">\n");
+    push_element(&mut xml, 5, "name", "Hela");
+    push_element(&mut xml, 5, "version", env!("CARGO_PKG_VERSION"));
+    xml.push_str("        </component>\n      </components>\n    </tools>\n");
+    xml.push_str(&format!(
+        "    <component type=\"application\" bom-ref=\"{}\">\n",
+        escape_xml(&root_ref(project))
+    ));
+    push_element(&mut xml, 3, "name", project);
+    xml.push_str("    </component>\n  </metadata>\n");
+
+    xml.push_str("  <components>\n");
+    for component in inventory.components.iter() {
+        xml.push_str(&format!(
+            "    <component type=\"{}\" bom-ref=\"{}\">\n",
+            escape_xml(&component.component_type),
+            escape_xml(&component.bom_ref)
+        ));
+        push_element(&mut xml, 3, "group", &component.group);
+        push_element(&mut xml, 3, "name", &component.name);
+        push_element(&mut xml, 3, "version", &component.version);
+        if !component.hashes.is_empty() {
+            xml.push_str("      <hashes>\n");
+            for hash in component.hashes.iter() {
+                xml.push_str(&format!(
+                    "        <hash alg=\"{}\">{}</hash>\n",
+                    escape_xml(&hash.alg),
+                    escape_xml(&hash.content)
+                ));
+            }
+            xml.push_str("      </hashes>\n");
+        }
+        let licenses = component_licenses(component);
+        if !licenses.is_empty() {
+            xml.push_str("      <licenses>\n");
+            for license in licenses {
+                match license {
+                    License::Id(id) => {
+                        xml.push_str("        <license>\n");
+                        push_element(&mut xml, 5, "id", id);
+                        xml.push_str("        </license>\n");
+                    }
+                    License::Name(name) => {
+                        xml.push_str("        <license>\n");
+                        push_element(&mut xml, 5, "name", name);
+                        xml.push_str("        </license>\n");
+                    }
+                    License::Expression(expression) => {
+                        push_element(&mut xml, 4, "expression", expression);
+                    }
+                }
+            }
+            xml.push_str("      </licenses>\n");
+        }
+        push_element(&mut xml, 3, "purl", &component.purl);
+        xml.push_str("      <properties>\n");
+        for manifest in component.manifests.iter() {
+            xml.push_str(&format!(
+                "        <property name=\"hela:manifest\">{}</property>\n",
+                escape_xml(manifest)
+            ));
+        }
+        xml.push_str("      </properties>\n");
+        xml.push_str("    </component>\n");
+    }
+    xml.push_str("  </components>\n");
+
+    xml.push_str("  <dependencies>\n");
+    xml.push_str(&format!(
+        "    <dependency ref=\"{}\">\n",
+        escape_xml(&root_ref(project))
+    ));
+    for child in inventory.root_dependencies.iter() {
+        xml.push_str(&format!(
+            "      <dependency ref=\"{}\"/>\n",
+            escape_xml(child)
+        ));
+    }
+    xml.push_str("    </dependency>\n");
+    for dependency in inventory.dependencies.iter() {
+        xml.push_str(&format!(
+            "    <dependency ref=\"{}\">\n",
+            escape_xml(&dependency.bom_ref)
+        ));
+        for child in dependency.depends_on.iter() {
+            xml.push_str(&format!(
+                "      <dependency ref=\"{}\"/>\n",
+                escape_xml(child)
+            ));
+        }
+        xml.push_str("    </dependency>\n");
+    }
+    xml.push_str("  </dependencies>\n");
+
+    if !inventory.vulnerabilities.is_empty() {
+        xml.push_str("  <vulnerabilities>\n");
+        for vuln in inventory.vulnerabilities.iter() {
+            xml.push_str(&format!(
+                "    <vulnerability bom-ref=\"{}\">\n",
+                escape_xml(&vuln.id)
+            ));
+            push_element(&mut xml, 3, "id", &vuln.id);
+            xml.push_str("      <source>\n");
+            push_element(&mut xml, 4, "name", "OSV");
+            push_element(
+                &mut xml,
+                4,
+                "url",
+                &format!("https://osv.dev/vulnerability/{}", vuln.id),
+            );
+            xml.push_str("      </source>\n");
+            if !vuln.aliases.is_empty() {
+                xml.push_str("      <references>\n");
+                for alias in vuln.aliases.iter() {
+                    xml.push_str("        <reference>\n");
+                    push_element(&mut xml, 5, "id", alias);
+                    xml.push_str("          <source>\n");
+                    push_element(&mut xml, 6, "name", "OSV");
+                    xml.push_str("          </source>\n");
+                    xml.push_str("        </reference>\n");
+                }
+                xml.push_str("      </references>\n");
+            }
+            xml.push_str("      <ratings>\n        <rating>\n");
+            push_element(&mut xml, 5, "severity", cyclonedx_severity(&vuln.severity));
+            push_element(&mut xml, 5, "method", "other");
+            xml.push_str("        </rating>\n      </ratings>\n");
+            let cwes = vuln
+                .cwes
+                .iter()
+                .filter_map(|x| cwe_number(x))
+                .collect::<Vec<u64>>();
+            if !cwes.is_empty() {
+                xml.push_str("      <cwes>\n");
+                for cwe in cwes {
+                    push_element(&mut xml, 4, "cwe", &cwe.to_string());
+                }
+                xml.push_str("      </cwes>\n");
+            }
+            push_element(&mut xml, 3, "description", &vuln.summary);
+            push_element(&mut xml, 3, "detail", &vuln.details);
+            xml.push_str("      <analysis>\n");
+            push_element(&mut xml, 4, "state", "in_triage");
+            xml.push_str("      </analysis>\n");
+            xml.push_str("      <affects>\n");
+            for affected in vuln.affects.iter() {
+                xml.push_str("        <target>\n");
+                push_element(&mut xml, 5, "ref", affected);
+                xml.push_str("        </target>\n");
+            }
+            xml.push_str("      </affects>\n");
+            xml.push_str("    </vulnerability>\n");
+        }
+        xml.push_str("  </vulnerabilities>\n");
+    }
+    xml.push_str("</bom>\n");
+    xml
+}
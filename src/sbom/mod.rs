@@ -0,0 +1,354 @@
+pub mod cyclonedx;
+pub mod reader;
+pub mod spdx;
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+// LicenseTool keeps the cdxgen output of every manifest under the "sbom" key of
+// /tmp/output.json in the format {"<manifest>": {"root": "", "components": [], "dependencies": []}},
+// everything in this module works on top of that data so we never need to re-run cdxgen.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum License {
+    Id(String),
+    Name(String),
+    Expression(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Hash {
+    pub alg: String,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Component {
+    pub bom_ref: String,
+    pub component_type: String,
+    pub group: String,
+    pub name: String,
+    pub version: String,
+    pub purl: String,
+    pub hashes: Vec<Hash>,
+    pub licenses: Vec<License>,
+    pub copyright: String,
+    pub manifests: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Dependency {
+    pub bom_ref: String,
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Vulnerability {
+    pub id: String,
+    pub severity: String,
+    pub summary: String,
+    pub details: String,
+    pub cwes: Vec<String>,
+    pub aliases: Vec<String>,
+    pub affects: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Inventory {
+    pub components: Vec<Component>,
+    // direct dependencies of the scanned project itself
+    pub root_dependencies: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+impl Component {
+    fn from_cdx(component: &Value, manifest: &str) -> Option<Component> {
+        let name = component["name"].as_str()?.to_string();
+        let version = component["version"].as_str().unwrap_or("").to_string();
+        let group = component["group"].as_str().unwrap_or("").to_string();
+        let purl = component["purl"].as_str().unwrap_or("").to_string();
+        let bom_ref = match component["bom-ref"].as_str() {
+            Some(bom_ref) => bom_ref.to_string(),
+            None if !purl.is_empty() => purl.clone(),
+            None => format!("{}@{}", name, version),
+        };
+        let mut hashes = Vec::new();
+        for hash in component["hashes"].as_array().unwrap_or(&vec![]) {
+            if let (Some(alg), Some(content)) = (hash["alg"].as_str(), hash["content"].as_str()) {
+                hashes.push(Hash {
+                    alg: alg.to_string(),
+                    content: content.to_string(),
+                });
+            }
+        }
+        let mut licenses = Vec::new();
+        for choice in component["licenses"].as_array().unwrap_or(&vec![]) {
+            if let Some(expression) = choice["expression"].as_str() {
+                licenses.push(License::Expression(expression.to_string()));
+            } else if let Some(id) = choice["license"]["id"].as_str() {
+                licenses.push(License::Id(id.to_string()));
+            } else if let Some(name) = choice["license"]["name"].as_str() {
+                licenses.push(License::Name(name.to_string()));
+            }
+        }
+        Some(Component {
+            bom_ref,
+            component_type: component["type"].as_str().unwrap_or("library").to_string(),
+            group,
+            name,
+            version,
+            purl,
+            hashes,
+            licenses,
+            copyright: component["copyright"].as_str().unwrap_or("").to_string(),
+            manifests: vec![manifest.to_string()],
+        })
+    }
+
+    fn key(&self) -> String {
+        if !self.purl.is_empty() {
+            return self.purl.clone();
+        }
+        format!("{}/{}@{}", self.group, self.name, self.version)
+    }
+
+    // names under which an OSV package can refer to this component
+    fn lookup_names(&self) -> Vec<String> {
+        let mut names = vec![self.name.to_lowercase()];
+        if !self.group.is_empty() {
+            names.push(format!("{}:{}", self.group, self.name).to_lowercase());
+            names.push(format!("{}/{}", self.group, self.name).to_lowercase());
+        }
+        names
+    }
+
+    fn merge(&mut self, other: &Component) {
+        for hash in other.hashes.iter() {
+            if !self.hashes.iter().any(|h| h.alg == hash.alg) {
+                self.hashes.push(hash.clone());
+            }
+        }
+        for license in other.licenses.iter() {
+            if !self.licenses.contains(license) {
+                self.licenses.push(license.clone());
+            }
+        }
+        if self.copyright.is_empty() {
+            self.copyright = other.copyright.clone();
+        }
+        for manifest in other.manifests.iter() {
+            if !self.manifests.contains(manifest) {
+                self.manifests.push(manifest.clone());
+            }
+        }
+    }
+}
+
+pub fn purl_for_package(ecosystem: &str, name: &str, version: &str) -> String {
+    let purl_type = match ecosystem.to_lowercase().as_str() {
+        "pypi" => "pypi",
+        "maven" => "maven",
+        "go" => "golang",
+        "crates.io" => "cargo",
+        "rubygems" => "gem",
+        "nuget" => "nuget",
+        "packagist" => "composer",
+        _ => "npm",
+    };
+    let name = if purl_type == "maven" {
+        name.replacen(':', "/", 1)
+    } else {
+        name.replace('@', "%40")
+    };
+    if version.is_empty() {
+        return format!("pkg:{}/{}", purl_type, name);
+    }
+    format!("pkg:{}/{}@{}", purl_type, name, version)
+}
+
+// Merge the per manifest cdxgen data and the osv-scanner results into one inventory
+// for the whole repository, components found in several manifests are listed only once.
+pub fn inventory_from_output(output_json: &Value) -> Inventory {
+    let mut inventory = Inventory::default();
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    // cdxgen bom-ref -> bom-ref of the merged component
+    let mut ref_mapping: HashMap<String, String> = HashMap::new();
+    let mut roots = Vec::new();
+
+    if let Some(manifests) = output_json["sbom"].as_object() {
+        for (manifest, sbom) in manifests {
+            if let Some(root) = sbom["root"].as_str() {
+                roots.push(root.to_string());
+            }
+            for component in sbom["components"].as_array().unwrap_or(&vec![]) {
+                let component = match Component::from_cdx(component, manifest) {
+                    Some(component) => component,
+                    None => continue,
+                };
+                let key = component.key();
+                match index_by_key.get(&key) {
+                    Some(index) => {
+                        let existing = &mut inventory.components[*index];
+                        existing.merge(&component);
+                        ref_mapping.insert(component.bom_ref.clone(), existing.bom_ref.clone());
+                    }
+                    None => {
+                        ref_mapping.insert(component.bom_ref.clone(), component.bom_ref.clone());
+                        index_by_key.insert(key, inventory.components.len());
+                        inventory.components.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut dependency_graph: HashMap<String, Vec<String>> = HashMap::new();
+        for sbom in manifests.values() {
+            for dependency in sbom["dependencies"].as_array().unwrap_or(&vec![]) {
+                let dependency_ref = match dependency["ref"].as_str() {
+                    Some(dependency_ref) => dependency_ref,
+                    None => continue,
+                };
+                let depends_on = dependency["dependsOn"]
+                    .as_array()
+                    .unwrap_or(&vec![])
+                    .iter()
+                    .filter_map(|x| x.as_str())
+                    .filter_map(|x| ref_mapping.get(x).cloned())
+                    .collect::<Vec<String>>();
+                if roots.iter().any(|root| root == dependency_ref) {
+                    for child in depends_on {
+                        if !inventory.root_dependencies.contains(&child) {
+                            inventory.root_dependencies.push(child);
+                        }
+                    }
+                    continue;
+                }
+                let dependency_ref = match ref_mapping.get(dependency_ref) {
+                    Some(dependency_ref) => dependency_ref.clone(),
+                    None => continue,
+                };
+                let entry = dependency_graph.entry(dependency_ref).or_default();
+                for child in depends_on {
+                    if !entry.contains(&child) {
+                        entry.push(child);
+                    }
+                }
+            }
+        }
+        // keep the order of components so the output is stable between runs
+        for component in inventory.components.iter() {
+            if let Some(depends_on) = dependency_graph.remove(&component.bom_ref) {
+                inventory.dependencies.push(Dependency {
+                    bom_ref: component.bom_ref.clone(),
+                    depends_on,
+                });
+            }
+        }
+    }
+
+    if let Some(manifests) = output_json["sca"].as_object() {
+        for (manifest, sca_result) in manifests {
+            for package in sca_result["packages"].as_array().unwrap_or(&vec![]) {
+                let name = package["package"]["name"].as_str().unwrap_or("");
+                let version = package["package"]["version"].as_str().unwrap_or("");
+                let ecosystem = package["package"]["ecosystem"].as_str().unwrap_or("");
+                if name.is_empty() {
+                    continue;
+                }
+                let bom_ref =
+                    inventory_component_ref(&mut inventory, manifest, ecosystem, name, version);
+                for vuln in package["vulnerabilities"].as_array().unwrap_or(&vec![]) {
+                    let id = match vuln["id"].as_str() {
+                        Some(id) => id.to_string(),
+                        None => continue,
+                    };
+                    if let Some(existing) =
+                        inventory.vulnerabilities.iter_mut().find(|v| v.id == id)
+                    {
+                        if !existing.affects.contains(&bom_ref) {
+                            existing.affects.push(bom_ref.clone());
+                        }
+                        continue;
+                    }
+                    let mut severity = vuln["database_specific"]["severity"]
+                        .as_str()
+                        .unwrap_or("unknown")
+                        .to_lowercase();
+                    if severity == "moderate" {
+                        severity = "medium".to_string();
+                    }
+                    inventory.vulnerabilities.push(Vulnerability {
+                        id,
+                        severity,
+                        summary: vuln["summary"].as_str().unwrap_or("").to_string(),
+                        details: vuln["details"].as_str().unwrap_or("").to_string(),
+                        cwes: string_list(&vuln["database_specific"]["cwe_ids"])
+                            .into_iter()
+                            .chain(string_list(&vuln["database_specific"]["cwe_id"]))
+                            .collect(),
+                        aliases: string_list(&vuln["aliases"]),
+                        affects: vec![bom_ref.clone()],
+                    });
+                }
+            }
+        }
+    }
+    inventory
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter_map(|x| x.as_str())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+// Find the component an osv-scanner package refers to, packages which cdxgen did not
+// report (e.g. when the license scan was skipped) are added to the inventory.
+fn inventory_component_ref(
+    inventory: &mut Inventory,
+    manifest: &str,
+    ecosystem: &str,
+    name: &str,
+    version: &str,
+) -> String {
+    let lookup_name = name.to_lowercase();
+    if let Some(component) = inventory
+        .components
+        .iter()
+        .find(|c| c.version == version && c.lookup_names().contains(&lookup_name))
+    {
+        return component.bom_ref.clone();
+    }
+    let purl = purl_for_package(ecosystem, name, version);
+    inventory.components.push(Component {
+        bom_ref: purl.clone(),
+        component_type: "library".to_string(),
+        name: name.to_string(),
+        version: version.to_string(),
+        purl: purl.clone(),
+        manifests: vec![manifest.to_string()],
+        ..Default::default()
+    });
+    inventory.root_dependencies.push(purl.clone());
+    purl
+}
+
+pub fn project_name(code_path: &str) -> String {
+    let mut code_path = code_path.to_string();
+    if !code_path.starts_with("http") {
+        if let Ok(path) = std::path::Path::new(&code_path).canonicalize() {
+            code_path = path.to_string_lossy().to_string();
+        }
+    }
+    let code_path = code_path.trim_end_matches('/').trim_end_matches(".git");
+    match code_path.split('/').next_back() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => "project".to_string(),
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+// Read a CycloneDX (JSON) or SPDX (JSON or tag-value) SBOM received from a third party
+// and convert it to the same {"root": "", "components": [], "dependencies": []} format
+// LicenseTool stores for cdxgen output, so the rest of Hela can treat both the same way.
+pub fn load_sbom(path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let data = std::fs::read_to_string(path)?;
+    let trimmed = data.trim_start();
+    if trimmed.starts_with('<') {
+        return Err(
+            "CycloneDX XML is not supported, please provide the SBOM in JSON format".into(),
+        );
+    }
+    if trimmed.starts_with("SPDXVersion:") || trimmed.starts_with('#') {
+        return Ok(from_spdx(&spdx_tag_value_to_json(&data)));
+    }
+    let sbom = serde_json::from_str::<Value>(&data)?;
+    if sbom["bomFormat"].as_str() == Some("CycloneDX") {
+        return Ok(from_cyclonedx(&sbom));
+    }
+    if sbom["spdxVersion"].is_string() {
+        return Ok(from_spdx(&sbom));
+    }
+    Err(format!("{} is neither a CycloneDX nor a SPDX document", path).into())
+}
+
+fn from_cyclonedx(sbom: &Value) -> Value {
+    // nested components are flattened, hela does not care about the assembly structure
+    let mut components = Vec::new();
+    let mut pending = sbom["components"].as_array().cloned().unwrap_or_default();
+    while let Some(mut component) = pending.pop() {
+        if let Some(children) = component["components"].as_array() {
+            pending.extend(children.iter().cloned());
+        }
+        if let Some(component) = component.as_object_mut() {
+            component.remove("components");
+        }
+        components.push(component);
+    }
+    components.reverse();
+    json!({
+        "root": sbom["metadata"]["component"]["bom-ref"],
+        "components": components,
+        "dependencies": sbom["dependencies"],
+    })
+}
+
+fn spdx_license(expression: &str) -> Option<Value> {
+    let expression = expression.trim();
+    if expression.is_empty() || expression == "NOASSERTION" || expression == "NONE" {
+        return None;
+    }
+    if expression.contains(' ') {
+        return Some(json!({ "expression": expression }));
+    }
+    Some(json!({ "license": { "id": expression } }))
+}
+
+// SPDX uses SHA256 while CycloneDX expects SHA-256
+fn cyclonedx_hash_alg(alg: &str) -> String {
+    match alg {
+        "SHA1" | "SHA224" | "SHA256" | "SHA384" | "SHA512" => alg.replacen("SHA", "SHA-", 1),
+        _ => alg.to_string(),
+    }
+}
+
+fn from_spdx(sbom: &Value) -> Value {
+    let empty = vec![];
+    let relationships = sbom["relationships"].as_array().unwrap_or(&empty);
+    let mut roots: Vec<String> = sbom["documentDescribes"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|x| x.as_str())
+        .map(|x| x.to_string())
+        .collect();
+    let mut depends_on: HashMap<String, Vec<String>> = HashMap::new();
+    for relationship in relationships {
+        let element = relationship["spdxElementId"].as_str().unwrap_or("");
+        let related = relationship["relatedSpdxElement"].as_str().unwrap_or("");
+        match relationship["relationshipType"].as_str().unwrap_or("") {
+            "DESCRIBES" if element == "SPDXRef-DOCUMENT" => roots.push(related.to_string()),
+            "DESCRIBED_BY" if related == "SPDXRef-DOCUMENT" => roots.push(element.to_string()),
+            "DEPENDS_ON" | "CONTAINS" => depends_on
+                .entry(element.to_string())
+                .or_default()
+                .push(related.to_string()),
+            "DEPENDENCY_OF" | "CONTAINED_BY" => depends_on
+                .entry(related.to_string())
+                .or_default()
+                .push(element.to_string()),
+            _ => {}
+        }
+    }
+
+    let mut components = Vec::new();
+    for package in sbom["packages"].as_array().unwrap_or(&empty) {
+        let spdx_id = package["SPDXID"].as_str().unwrap_or("");
+        if roots.iter().any(|x| x == spdx_id) {
+            continue;
+        }
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": spdx_id,
+            "name": package["name"],
+        });
+        if let Some(version) = package["versionInfo"].as_str() {
+            component["version"] = json!(version);
+        }
+        for external_ref in package["externalRefs"].as_array().unwrap_or(&empty) {
+            if external_ref["referenceType"].as_str() == Some("purl") {
+                component["purl"] = external_ref["referenceLocator"].clone();
+            }
+        }
+        // concluded license is the result of an analysis, prefer it over the declared one
+        let license = package["licenseConcluded"]
+            .as_str()
+            .and_then(spdx_license)
+            .or_else(|| package["licenseDeclared"].as_str().and_then(spdx_license));
+        if let Some(license) = license {
+            component["licenses"] = json!([license]);
+        }
+        let hashes = package["checksums"]
+            .as_array()
+            .unwrap_or(&empty)
+            .iter()
+            .map(|x| {
+                json!({
+                    "alg": cyclonedx_hash_alg(x["algorithm"].as_str().unwrap_or("")),
+                    "content": x["checksumValue"],
+                })
+            })
+            .collect::<Vec<Value>>();
+        if !hashes.is_empty() {
+            component["hashes"] = json!(hashes);
+        }
+        components.push(component);
+    }
+
+    let dependencies = depends_on
+        .into_iter()
+        .map(|(element, related)| json!({ "ref": element, "dependsOn": related }))
+        .collect::<Vec<Value>>();
+    json!({
+        "root": roots.first(),
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+// Convert the SPDX tag-value format to the JSON structure, only the fields hela uses
+// are converted
+fn spdx_tag_value_to_json(data: &str) -> Value {
+    let mut packages: Vec<Value> = Vec::new();
+    let mut relationships = Vec::new();
+    for line in data.lines() {
+        let (tag, value) = match line.split_once(':') {
+            Some((tag, value)) => (tag.trim(), value.trim()),
+            None => continue,
+        };
+        if tag == "Relationship" {
+            let parts = value.split_whitespace().collect::<Vec<&str>>();
+            if parts.len() == 3 {
+                relationships.push(json!({
+                    "spdxElementId": parts[0],
+                    "relationshipType": parts[1],
+                    "relatedSpdxElement": parts[2],
+                }));
+            }
+            continue;
+        }
+        if tag == "PackageName" {
+            packages.push(json!({ "name": value }));
+            continue;
+        }
+        let package = match packages.last_mut() {
+            Some(package) => package,
+            None => continue,
+        };
+        match tag {
+            "SPDXID" => package["SPDXID"] = json!(value),
+            "PackageVersion" => package["versionInfo"] = json!(value),
+            "PackageLicenseConcluded" => package["licenseConcluded"] = json!(value),
+            "PackageLicenseDeclared" => package["licenseDeclared"] = json!(value),
+            "PackageChecksum" => {
+                if let Some((alg, checksum)) = value.split_once(':') {
+                    if !package["checksums"].is_array() {
+                        package["checksums"] = json!([]);
+                    }
+                    package["checksums"].as_array_mut().unwrap().push(json!({
+                        "algorithm": alg.trim(),
+                        "checksumValue": checksum.trim(),
+                    }));
+                }
+            }
+            "ExternalRef" => {
+                let parts = value.split_whitespace().collect::<Vec<&str>>();
+                if parts.len() == 3 {
+                    if !package["externalRefs"].is_array() {
+                        package["externalRefs"] = json!([]);
+                    }
+                    package["externalRefs"].as_array_mut().unwrap().push(json!({
+                        "referenceCategory": parts[0],
+                        "referenceType": parts[1],
+                        "referenceLocator": parts[2],
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+// Minimal CycloneDX document which osv-scanner understands, used to look up
+// vulnerabilities of SBOM components without the source tree
+pub fn osv_scanner_sbom(sbom: &Value) -> Value {
+    let components = sbom["components"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter(|x| x["purl"].is_string())
+        .map(|x| {
+            json!({
+                "type": "library",
+                "bom-ref": x["purl"],
+                "name": x["name"],
+                "version": x["version"],
+                "purl": x["purl"],
+            })
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "components": components,
+    })
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use mongodb::bson::uuid;
+use serde_json::{json, Value};
+
+use super::{Component, Inventory, License};
+
+pub const SPEC_VERSION: &str = "SPDX-2.3";
+
+const ROOT_ID: &str = "SPDXRef-RootPackage";
+
+struct Package {
+    spdx_id: String,
+    name: String,
+    version: String,
+    purl: String,
+    checksums: Vec<(String, String)>,
+    license: String,
+    advisories: Vec<String>,
+}
+
+struct Document {
+    name: String,
+    namespace: String,
+    created: String,
+    packages: Vec<Package>,
+    // (element, relationship, related element)
+    relationships: Vec<(String, String, String)>,
+    // LicenseRef id -> license name, for licenses which are not on the SPDX list
+    extracted_licenses: Vec<(String, String)>,
+}
+
+fn sanitize_id(input: &str) -> String {
+    input
+        .chars()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || x == '.' || x == '-' {
+                x
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+// CycloneDX uses names like SHA-256 while SPDX expects SHA256
+fn checksum_algorithm(alg: &str) -> Option<String> {
+    let alg = alg.to_uppercase();
+    match alg.as_str() {
+        "SHA-1" | "SHA-224" | "SHA-256" | "SHA-384" | "SHA-512" => Some(alg.replace('-', "")),
+        "MD5" | "SHA3-256" | "SHA3-384" | "SHA3-512" => Some(alg),
+        "BLAKE2B-256" | "BLAKE2B-384" | "BLAKE2B-512" => Some(alg.replace("BLAKE2B", "BLAKE2b")),
+        "BLAKE3" => Some(alg),
+        _ => None,
+    }
+}
+
+fn declared_license(
+    component: &Component,
+    extracted_licenses: &mut Vec<(String, String)>,
+) -> String {
+    let mut parts = Vec::new();
+    for license in component.licenses.iter() {
+        let part = match license {
+            License::Id(id) => id.clone(),
+            License::Expression(expression) => {
+                if component.licenses.len() > 1 {
+                    format!("({})", expression)
+                } else {
+                    expression.clone()
+                }
+            }
+            License::Name(name) => {
+                let license_ref = format!("LicenseRef-{}", sanitize_id(name));
+                if !extracted_licenses.iter().any(|(id, _)| id == &license_ref) {
+                    extracted_licenses.push((license_ref.clone(), name.clone()));
+                }
+                license_ref
+            }
+        };
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    if parts.is_empty() {
+        return "NOASSERTION".to_string();
+    }
+    parts.join(" AND ")
+}
+
+fn build_document(inventory: &Inventory, project: &str) -> Document {
+    let mut extracted_licenses = Vec::new();
+    let mut packages = Vec::new();
+    let mut ids_by_ref: HashMap<String, String> = HashMap::new();
+    for (index, component) in inventory.components.iter().enumerate() {
+        let spdx_id = format!(
+            "SPDXRef-Package-{}-{}",
+            index + 1,
+            sanitize_id(&component.name)
+        );
+        ids_by_ref.insert(component.bom_ref.clone(), spdx_id.clone());
+        let name = if component.group.is_empty() {
+            component.name.clone()
+        } else {
+            format!("{}/{}", component.group, component.name)
+        };
+        let advisories = inventory
+            .vulnerabilities
+            .iter()
+            .filter(|x| x.affects.contains(&component.bom_ref))
+            .map(|x| format!("https://osv.dev/vulnerability/{}", x.id))
+            .collect();
+        packages.push(Package {
+            spdx_id,
+            name,
+            version: component.version.clone(),
+            purl: component.purl.clone(),
+            checksums: component
+                .hashes
+                .iter()
+                .filter_map(|x| checksum_algorithm(&x.alg).map(|alg| (alg, x.content.clone())))
+                .collect(),
+            license: declared_license(component, &mut extracted_licenses),
+            advisories,
+        });
+    }
+
+    let mut relationships = vec![(
+        "SPDXRef-DOCUMENT".to_string(),
+        "DESCRIBES".to_string(),
+        ROOT_ID.to_string(),
+    )];
+    for child in inventory.root_dependencies.iter() {
+        if let Some(child) = ids_by_ref.get(child) {
+            relationships.push((ROOT_ID.to_string(), "DEPENDS_ON".to_string(), child.clone()));
+        }
+    }
+    for dependency in inventory.dependencies.iter() {
+        let parent = match ids_by_ref.get(&dependency.bom_ref) {
+            Some(parent) => parent,
+            None => continue,
+        };
+        for child in dependency.depends_on.iter() {
+            if let Some(child) = ids_by_ref.get(child) {
+                relationships.push((parent.clone(), "DEPENDS_ON".to_string(), child.clone()));
+            }
+        }
+    }
+
+    Document {
+        name: project.to_string(),
+        namespace: format!(
+            "https://spdx.org/spdxdocs/hela-{}-{}",
+            sanitize_id(project),
+            uuid::Uuid::new()
+        ),
+        created: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        packages,
+        relationships,
+        extracted_licenses,
+    }
+}
+
+pub fn to_json(inventory: &Inventory, project: &str) -> Value {
+    let document = build_document(inventory, project);
+    let mut packages = vec![json!({
+        "name": document.name,
+        "SPDXID": ROOT_ID,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": "NOASSERTION",
+        "copyrightText": "NOASSERTION",
+        "primaryPackagePurpose": "APPLICATION",
+    })];
+    for package in document.packages.iter() {
+        let mut value = json!({
+            "name": package.name,
+            "SPDXID": package.spdx_id,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            // hela only reports what the package declares, it draws no conclusion
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": package.license,
+            "copyrightText": "NOASSERTION",
+        });
+        if !package.version.is_empty() {
+            value["versionInfo"] = json!(package.version);
+        }
+        if !package.checksums.is_empty() {
+            value["checksums"] = Value::Array(
+                package
+                    .checksums
+                    .iter()
+                    .map(|(alg, content)| json!({ "algorithm": alg, "checksumValue": content }))
+                    .collect(),
+            );
+        }
+        let mut external_refs = Vec::new();
+        if !package.purl.is_empty() {
+            external_refs.push(json!({
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": package.purl,
+            }));
+        }
+        for advisory in package.advisories.iter() {
+            external_refs.push(json!({
+                "referenceCategory": "SECURITY",
+                "referenceType": "advisory",
+                "referenceLocator": advisory,
+            }));
+        }
+        if !external_refs.is_empty() {
+            value["externalRefs"] = Value::Array(external_refs);
+        }
+        packages.push(value);
+    }
+
+    let mut value = json!({
+        "spdxVersion": SPEC_VERSION,
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": document.name,
+        "documentNamespace": document.namespace,
+        "creationInfo": {
+            "created": document.created,
+            "creators": [format!("Tool: Hela-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "documentDescribes": [ROOT_ID],
+        "packages": packages,
+        "relationships": document
+            .relationships
+            .iter()
+            .map(|(element, relationship, related)| json!({
+                "spdxElementId": element,
+                "relationshipType": relationship,
+                "relatedSpdxElement": related,
+            }))
+            .collect::<Vec<Value>>(),
+    });
+    if !document.extracted_licenses.is_empty() {
+        value["hasExtractedLicensingInfos"] = Value::Array(
+            document
+                .extracted_licenses
+                .iter()
+                .map(|(id, name)| json!({ "licenseId": id, "name": name, "extractedText": name }))
+                .collect(),
+        );
+    }
+    value
+}
+
+pub fn to_tag_value(inventory: &Inventory, project: &str) -> String {
+    let document = build_document(inventory, project);
+    let mut lines = vec![
+        format!("SPDXVersion: {}", SPEC_VERSION),
+        "DataLicense: CC0-1.0".to_string(),
+        "SPDXID: SPDXRef-DOCUMENT".to_string(),
+        format!("DocumentName: {}", document.name),
+        format!("DocumentNamespace: {}", document.namespace),
+        format!("Creator: Tool: Hela-{}", env!("CARGO_PKG_VERSION")),
+        format!("Created: {}", document.created),
+        String::new(),
+        format!("PackageName: {}", document.name),
+        format!("SPDXID: {}", ROOT_ID),
+        "PackageDownloadLocation: NOASSERTION".to_string(),
+        "FilesAnalyzed: false".to_string(),
+        "PackageLicenseConcluded: NOASSERTION".to_string(),
+        "PackageLicenseDeclared: NOASSERTION".to_string(),
+        "PackageCopyrightText: NOASSERTION".to_string(),
+        "PrimaryPackagePurpose: APPLICATION".to_string(),
+    ];
+    for package in document.packages.iter() {
+        lines.push(String::new());
+        lines.push(format!("PackageName: {}", package.name));
+        lines.push(format!("SPDXID: {}", package.spdx_id));
+        if !package.version.is_empty() {
+            lines.push(format!("PackageVersion: {}", package.version));
+        }
+        lines.push("PackageDownloadLocation: NOASSERTION".to_string());
+        lines.push("FilesAnalyzed: false".to_string());
+        for (alg, content) in package.checksums.iter() {
+            lines.push(format!("PackageChecksum: {}: {}", alg, content));
+        }
+        lines.push("PackageLicenseConcluded: NOASSERTION".to_string());
+        lines.push(format!("PackageLicenseDeclared: {}", package.license));
+        lines.push("PackageCopyrightText: NOASSERTION".to_string());
+        if !package.purl.is_empty() {
+            lines.push(format!(
+                "ExternalRef: PACKAGE-MANAGER purl {}",
+                package.purl
+            ));
+        }
+        for advisory in package.advisories.iter() {
+            lines.push(format!("ExternalRef: SECURITY advisory {}", advisory));
+        }
+    }
+    lines.push(String::new());
+    for (element, relationship, related) in document.relationships.iter() {
+        lines.push(format!(
+            "Relationship: {} {} {}",
+            element, relationship, related
+        ));
+    }
+    for (id, name) in document.extracted_licenses.iter() {
+        lines.push(String::new());
+        lines.push(format!("LicenseID: {}", id));
+        lines.push(format!("ExtractedText: <text>{}</text>", name));
+        lines.push(format!("LicenseName: {}", name));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::{reader::load_sbom, Dependency, Hash, Vulnerability};
+
+    fn inventory() -> Inventory {
+        let component = |name: &str, version: &str, licenses: Vec<License>| Component {
+            bom_ref: format!("pkg:npm/{}@{}", name, version),
+            component_type: "library".to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+            purl: format!("pkg:npm/{}@{}", name, version),
+            licenses,
+            ..Default::default()
+        };
+        let mut express = component("express", "4.18.2", vec![License::Id("MIT".to_string())]);
+        express.hashes.push(Hash {
+            alg: "SHA-256".to_string(),
+            content: "a".repeat(64),
+        });
+        let mut scoped = component(
+            "parser",
+            "1.0.0",
+            vec![License::Expression("MIT OR Apache-2.0".to_string())],
+        );
+        scoped.group = "@babel".to_string();
+        Inventory {
+            components: vec![
+                express,
+                scoped,
+                component(
+                    "internal",
+                    "0.1.0",
+                    vec![License::Name("Acme Proprietary".to_string())],
+                ),
+            ],
+            root_dependencies: vec!["pkg:npm/express@4.18.2".to_string()],
+            dependencies: vec![Dependency {
+                bom_ref: "pkg:npm/express@4.18.2".to_string(),
+                depends_on: vec!["pkg:npm/parser@1.0.0".to_string()],
+            }],
+            vulnerabilities: vec![Vulnerability {
+                id: "GHSA-rv95-896h-c2vc".to_string(),
+                severity: "MODERATE".to_string(),
+                summary: String::new(),
+                details: String::new(),
+                cwes: vec![],
+                aliases: vec![],
+                affects: vec!["pkg:npm/express@4.18.2".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn json_matches_spdx_schema() {
+        let schema: Value =
+            serde_json::from_str(include_str!("testdata/spdx-2.3.schema.json")).unwrap();
+        let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+        let document = to_json(&inventory(), "demo");
+        if let Err(errors) = schema.validate(&document) {
+            let errors: Vec<String> = errors
+                .map(|x| format!("{}: {}", x.instance_path, x))
+                .collect();
+            panic!("invalid SPDX document: {:?}", errors);
+        }
+
+        let packages = document["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 4);
+        assert!(packages
+            .iter()
+            .all(|x| x["licenseConcluded"] == "NOASSERTION"));
+        assert_eq!(packages[1]["licenseDeclared"], "MIT");
+        assert_eq!(packages[2]["name"], "@babel/parser");
+        assert_eq!(
+            packages[3]["licenseDeclared"],
+            "LicenseRef-Acme-Proprietary"
+        );
+        assert_eq!(
+            document["hasExtractedLicensingInfos"][0]["licenseId"],
+            "LicenseRef-Acme-Proprietary"
+        );
+    }
+
+    #[test]
+    fn tag_value_round_trips_through_reader() {
+        let path = std::env::temp_dir().join(format!("hela-spdx-{}.spdx", uuid::Uuid::new()));
+        std::fs::write(&path, to_tag_value(&inventory(), "demo")).unwrap();
+        let sbom = load_sbom(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        let sbom = sbom.unwrap();
+
+        assert_eq!(sbom["root"], ROOT_ID);
+        let components = sbom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0]["name"], "express");
+        assert_eq!(components[0]["version"], "4.18.2");
+        assert_eq!(components[0]["purl"], "pkg:npm/express@4.18.2");
+        assert_eq!(components[0]["licenses"][0]["license"]["id"], "MIT");
+        assert_eq!(components[0]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(
+            components[1]["licenses"][0]["expression"],
+            "MIT OR Apache-2.0"
+        );
+
+        let express = components[0]["bom-ref"].as_str().unwrap();
+        let parser = components[1]["bom-ref"].as_str().unwrap();
+        let depends_on = |element: &str| -> Vec<String> {
+            sbom["dependencies"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|x| x["ref"] == element)
+                .flat_map(|x| x["dependsOn"].as_array().unwrap().clone())
+                .map(|x| x.as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(depends_on(ROOT_ID), vec![express.to_string()]);
+        assert_eq!(depends_on(express), vec![parser.to_string()]);
+    }
+}
@@ -0,0 +1,203 @@
+use globset::{Glob, GlobMatcher};
+
+pub const IGNORE_FILE: &str = ".helaignore";
+const INLINE_MARKER: &str = "hela:ignore";
+const REASON_PREFIX: &str = "reason=";
+
+// What an ignore entry or comment is matched against
+pub struct IgnoreTarget<'a> {
+    // relative to the project root
+    pub path: &'a str,
+    // rule id, detector name or vulnerability id and aliases
+    pub rules: Vec<&'a str>,
+    // name and version of the vulnerable package, SCA only
+    pub package: Option<(&'a str, &'a str)>,
+    pub fingerprint: &'a str,
+}
+
+// semgrep prefixes rule ids with the rules directory, so `python.lang.eval` also
+// matches `tmp.sast-rules.python.lang.eval`
+fn rule_matches(pattern: &str, rules: &[&str]) -> bool {
+    rules.iter().any(|rule| {
+        rule.eq_ignore_ascii_case(pattern)
+            || rule
+                .to_lowercase()
+                .ends_with(&format!(".{}", pattern.to_lowercase()))
+    })
+}
+
+// Splits `<tokens...> reason=<text>` into the tokens and the reason
+fn split_reason(text: &str) -> (Vec<&str>, String) {
+    let (tokens, reason) = match text.find(REASON_PREFIX) {
+        Some(index) => (&text[..index], text[index + REASON_PREFIX.len()..].trim()),
+        None => (text, ""),
+    };
+    let tokens = tokens
+        .split(|x: char| x.is_whitespace() || x == ',')
+        .filter(|x| !x.is_empty())
+        .collect();
+    (tokens, reason.trim_matches('"').to_string())
+}
+
+// `hela:ignore <rule-id...> reason=...` in a comment of any language, a rule id and a
+// reason are required so a stray comment can't hide every finding next to it
+pub struct InlineIgnore {
+    pub rules: Vec<String>,
+    pub reason: String,
+}
+
+impl InlineIgnore {
+    // None if the line has no annotation, an error if the annotation is incomplete
+    pub fn parse(line: &str) -> Option<Result<InlineIgnore, String>> {
+        let index = line.find(INLINE_MARKER)?;
+        let rest = &line[index + INLINE_MARKER.len()..];
+        // `hela:ignore-foo` is not an annotation
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let rest = rest
+            .trim()
+            .trim_end_matches("-->")
+            .trim_end_matches("*/")
+            .trim();
+        let (rules, reason) = split_reason(rest);
+        if rules.is_empty() {
+            return Some(Err(format!("{} needs a rule id", INLINE_MARKER)));
+        }
+        if reason.is_empty() {
+            return Some(Err(format!("{} needs a {}", INLINE_MARKER, REASON_PREFIX)));
+        }
+        Some(Ok(InlineIgnore {
+            rules: rules.iter().map(|x| x.to_string()).collect(),
+            reason,
+        }))
+    }
+
+    pub fn matches(&self, target: &IgnoreTarget) -> bool {
+        self.rules.iter().any(|x| rule_matches(x, &target.rules))
+    }
+}
+
+// One line of .helaignore, all given qualifiers have to match:
+//   path:<glob> rule:<id> package:<name>[@<version>] fingerprint:<hash> reason=<text>
+// A bare glob is the same as path:<glob>.
+pub struct IgnoreEntry {
+    pub line: usize,
+    path: Option<GlobMatcher>,
+    rule: Option<String>,
+    package: Option<(String, Option<String>)>,
+    fingerprint: Option<String>,
+    pub reason: String,
+}
+
+fn path_matcher(pattern: &str) -> Result<GlobMatcher, String> {
+    let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+    // a directory ignores everything below it
+    let pattern = if pattern.ends_with('/') {
+        format!("{}**", pattern)
+    } else {
+        pattern.to_string()
+    };
+    Glob::new(&pattern)
+        .map(|x| x.compile_matcher())
+        .map_err(|e| e.to_string())
+}
+
+impl IgnoreEntry {
+    pub fn parse(text: &str, line: usize) -> Result<Option<IgnoreEntry>, String> {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            return Ok(None);
+        }
+        let (tokens, reason) = split_reason(text);
+        let mut entry = IgnoreEntry {
+            line,
+            path: None,
+            rule: None,
+            package: None,
+            fingerprint: None,
+            reason,
+        };
+        for token in tokens {
+            match token.split_once(':') {
+                Some(("rule", value)) => entry.rule = Some(value.to_string()),
+                Some(("fingerprint", value)) => entry.fingerprint = Some(value.to_lowercase()),
+                Some(("package", value)) => {
+                    // scoped npm packages start with @
+                    entry.package = Some(match value.rsplit_once('@') {
+                        Some((name, version)) if !name.is_empty() => {
+                            (name.to_string(), Some(version.to_string()))
+                        }
+                        _ => (value.to_string(), None),
+                    })
+                }
+                Some(("path", value)) => entry.path = Some(path_matcher(value)?),
+                _ => entry.path = Some(path_matcher(token)?),
+            }
+        }
+        if entry.path.is_none()
+            && entry.rule.is_none()
+            && entry.package.is_none()
+            && entry.fingerprint.is_none()
+        {
+            return Err("entry has no path, rule, package or fingerprint".to_string());
+        }
+        Ok(Some(entry))
+    }
+
+    pub fn matches(&self, target: &IgnoreTarget) -> bool {
+        if let Some(path) = &self.path {
+            if !path.is_match(target.path) {
+                return false;
+            }
+        }
+        if let Some(rule) = &self.rule {
+            if !rule_matches(rule, &target.rules) {
+                return false;
+            }
+        }
+        if let Some((name, version)) = &self.package {
+            match target.package {
+                Some((package_name, package_version)) => {
+                    if !name.eq_ignore_ascii_case(package_name)
+                        || version.as_ref().is_some_and(|x| x != package_version)
+                    {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        if let Some(fingerprint) = &self.fingerprint {
+            if fingerprint != target.fingerprint {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct IgnoreFile {
+    pub entries: Vec<IgnoreEntry>,
+}
+
+impl IgnoreFile {
+    // Invalid entries are skipped and returned as warnings
+    pub fn parse(data: &str) -> (IgnoreFile, Vec<String>) {
+        let mut entries = Vec::new();
+        let mut warnings = Vec::new();
+        for (index, line) in data.lines().enumerate() {
+            match IgnoreEntry::parse(line, index + 1) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => warnings.push(format!("{}:{}: {}", IGNORE_FILE, index + 1, e)),
+            }
+        }
+        (IgnoreFile { entries }, warnings)
+    }
+
+    pub fn matching_entry(&self, target: &IgnoreTarget) -> Option<&IgnoreEntry> {
+        self.entries.iter().find(|x| x.matches(target))
+    }
+}
@@ -1,2 +1,4 @@
 pub(crate) mod tools;
+pub(crate) mod ignore;
 pub(crate) mod scanner;
+pub(crate) mod suppressions;
@@ -1,3 +1,4 @@
+use crate::scans::suppressions::Suppressions;
 use crate::scans::tools::{
     license_tool::LicenseTool, sast_tool::SastTool, sca_tool::ScaTool, secret_tool::SecretTool,
 };
@@ -26,7 +27,7 @@ impl ScanRunner {
 
     pub async fn execute_scan(
         &self,
-        mongo_uri: &str,
+        suppressions: &Suppressions,
         scan_type: &str,
         path: &str,
         branch: Option<&str>,
@@ -41,20 +42,27 @@ impl ScanRunner {
         match scan_type {
             "sast" => {
                 self.sast_tool
-                    .run_scan(path, branch, pr_branch, rule_path, verbose)
+                    .run_scan(path, branch, pr_branch, rule_path, suppressions, verbose)
                     .await
             }
             "sca" => {
                 self.sca_tool
                     .run_scan(
-                        path, branch, pr_branch, no_install, root_only, build_args, manifests,
+                        path,
+                        branch,
+                        pr_branch,
+                        no_install,
+                        root_only,
+                        build_args,
+                        manifests,
+                        suppressions,
                         verbose,
                     )
                     .await
             }
             "secret" => {
                 self.secret_tool
-                    .run_scan(path, branch, pr_branch, mongo_uri, verbose)
+                    .run_scan(path, branch, pr_branch, suppressions, verbose)
                     .await
             }
             "license-compliance" => {
@@ -65,4 +73,15 @@ impl ScanRunner {
             _ => println!("Invalid scan type: {}", scan_type),
         }
     }
+
+    pub async fn execute_sbom_scan(&self, scan_type: &str, sbom_path: &str, verbose: bool) {
+        match scan_type {
+            "sca" => self.sca_tool.run_sbom_scan(sbom_path, verbose).await,
+            "license-compliance" => self.license_tool.run_sbom_scan(sbom_path, verbose).await,
+            _ => println!(
+                "[❕] Warning: {} scan needs the source code and can't run on a SBOM, skipping it",
+                scan_type
+            ),
+        }
+    }
 }
@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use tokio::sync::OnceCell;
+
+use super::ignore::{IgnoreFile, IgnoreTarget, InlineIgnore, IGNORE_FILE};
+use crate::{
+    secrets::{
+        allowlist::{load_allowlist, Allowlist},
+        finding_file, finding_line,
+    },
+    store::FindingStore,
+    utils::{
+        common::print_error,
+        fingerprint::{
+            context_hash, relative_path, sast_fingerprint, sca_fingerprint, secret_fingerprint,
+            Occurrences,
+        },
+    },
+};
+
+// Suppression data (allowlists, ignored findings) of a run, created once and shared by
+// every scanner. Each source is fetched on first use and cached for the rest of the run.
+pub struct Suppressions {
+    store: Option<Arc<dyn FindingStore>>,
+    secret_allowlist: OnceCell<Allowlist>,
+    ignore_file: OnceCell<IgnoreFile>,
+}
+
+// Lines of the files inline annotations are looked up in, each file is read once
+#[derive(Default)]
+struct SourceFiles {
+    files: HashMap<String, Vec<String>>,
+    // invalid annotations already warned about
+    invalid: HashSet<(String, usize)>,
+}
+
+impl SourceFiles {
+    fn lines(&mut self, file: &str) -> &[String] {
+        self.files.entry(file.to_string()).or_insert_with(|| {
+            std::fs::read_to_string(file)
+                .map(|x| x.lines().map(|x| x.to_string()).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    // lines of `file` (relative to the repository `root`) as of `commit`
+    fn revision_lines(&mut self, root: &str, commit: &str, file: &str) -> &[String] {
+        self.files
+            .entry(format!("{}:{}", commit, file))
+            .or_insert_with(|| {
+                std::process::Command::new("git")
+                    .args(["show", &format!("{}:{}", commit, file)])
+                    .current_dir(root)
+                    .output()
+                    .ok()
+                    .filter(|x| x.status.success())
+                    .map(|x| {
+                        String::from_utf8_lossy(&x.stdout)
+                            .lines()
+                            .map(|x| x.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+    }
+
+    // an annotation applies to its own line and the line below it
+    fn inline_ignore(
+        &mut self,
+        file: &str,
+        line: usize,
+        target: &IgnoreTarget,
+    ) -> Option<(usize, String)> {
+        for number in [line, line.saturating_sub(1)] {
+            if number == 0 {
+                continue;
+            }
+            let annotation = self
+                .lines(file)
+                .get(number - 1)
+                .and_then(|x| InlineIgnore::parse(x));
+            match annotation {
+                Some(Ok(ignore)) if ignore.matches(target) => {
+                    return Some((number, ignore.reason));
+                }
+                Some(Err(e)) if self.invalid.insert((file.to_string(), number)) => {
+                    print_error(
+                        &format!(
+                            "Warning: Skipping invalid annotation {}:{}: {}",
+                            target.path, number, e
+                        ),
+                        101,
+                    );
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn suppression(kind: &str, reason: &str, location: String) -> Value {
+    json!({
+        "kind": kind,
+        "justification": reason,
+        "location": location,
+    })
+}
+
+impl Suppressions {
+    pub fn new(store: Option<Arc<dyn FindingStore>>) -> Suppressions {
+        Suppressions {
+            store,
+            secret_allowlist: OnceCell::new(),
+            ignore_file: OnceCell::new(),
+        }
+    }
+
+    // Empty without a finding store or if it couldn't be loaded
+    pub async fn secret_allowlist(&self) -> &Allowlist {
+        self.secret_allowlist
+            .get_or_init(|| async {
+                let store = match &self.store {
+                    Some(store) => store.as_ref(),
+                    None => return Allowlist::default(),
+                };
+                if let Ok(plaintext) = store.plaintext_secrets().await {
+                    if !plaintext.is_empty() {
+                        print_error(
+                            &format!(
+                                "Warning: {} plaintext secret whitelist entries are ignored, run `hela allowlist migrate` to hash them",
+                                plaintext.len()
+                            ),
+                            101,
+                        );
+                    }
+                }
+                match load_allowlist(store).await {
+                    Ok(allowlist) => allowlist,
+                    Err(e) => {
+                        print_error(
+                            &format!("Warning: Unable to load the secret allowlist: {}", e),
+                            101,
+                        );
+                        Allowlist::default()
+                    }
+                }
+            })
+            .await
+    }
+
+    // .helaignore of the scanned project, empty if there is none
+    pub async fn ignore_file(&self, root: &str) -> &IgnoreFile {
+        self.ignore_file
+            .get_or_init(|| async {
+                let data = match std::fs::read_to_string(format!("{}/{}", root, IGNORE_FILE)) {
+                    Ok(data) => data,
+                    Err(_) => return IgnoreFile::default(),
+                };
+                let (ignore_file, warnings) = IgnoreFile::parse(&data);
+                for warning in warnings {
+                    print_error(&format!("Warning: Skipping invalid {}", warning), 101);
+                }
+                ignore_file
+            })
+            .await
+    }
+
+    // inline annotations take precedence so the reason next to the code is reported
+    fn find_suppression(
+        ignore_file: &IgnoreFile,
+        sources: &mut SourceFiles,
+        target: &IgnoreTarget,
+        inline: Option<(&str, usize)>,
+    ) -> Option<Value> {
+        if let Some((file, line)) = inline {
+            if let Some((number, reason)) = sources.inline_ignore(file, line, target) {
+                return Some(suppression(
+                    "inSource",
+                    &reason,
+                    format!("{}:{}", target.path, number),
+                ));
+            }
+        }
+        ignore_file.matching_entry(target).map(|entry| {
+            suppression(
+                "external",
+                &entry.reason,
+                format!("{}:{}", IGNORE_FILE, entry.line),
+            )
+        })
+    }
+
+    // The functions below set `fingerprint` of every finding and `suppression` of the
+    // ones ignored by a hela:ignore comment or .helaignore. Suppressed findings stay in
+    // the results so reports can list them.
+
+    pub async fn apply_sast(&self, results: &mut [Value], root: &str) {
+        let ignore_file = self.ignore_file(root).await;
+        let mut sources = SourceFiles::default();
+        let mut occurrences = Occurrences::default();
+        for result in results.iter_mut() {
+            let file = result["path"].as_str().unwrap_or("").to_string();
+            let path = relative_path(&file, root);
+            let line = result["start"]["line"].as_u64().unwrap_or(0) as usize;
+            let end = result["end"]["line"].as_u64().unwrap_or(0) as usize;
+            let source = sources.lines(&file);
+            let fingerprint = occurrences.disambiguate(sast_fingerprint(result, root, source));
+            if let Some(hash) = context_hash(source, line, end) {
+                result["context_hash"] = json!(hash);
+            }
+            let target = IgnoreTarget {
+                path: &path,
+                rules: vec![result["check_id"].as_str().unwrap_or("")],
+                package: None,
+                fingerprint: &fingerprint,
+            };
+            let found = Self::find_suppression(
+                ignore_file,
+                &mut sources,
+                &target,
+                Some((file.as_str(), line)),
+            );
+            if let Some(found) = found {
+                result["suppression"] = found;
+            }
+            result["fingerprint"] = json!(fingerprint);
+        }
+    }
+
+    pub async fn apply_secrets(&self, results: &mut [Value], root: &str) {
+        let ignore_file = self.ignore_file(root).await;
+        let mut sources = SourceFiles::default();
+        let mut occurrences = Occurrences::default();
+        for result in results.iter_mut() {
+            let file = finding_file(result).to_string();
+            let path = relative_path(&file, root);
+            let git = &result["SourceMetadata"]["Data"]["Git"];
+            // the line of a history finding is the one of its commit, not of the checkout,
+            // so it's fingerprinted with the file of that commit and a secret committed many
+            // times is still one finding
+            let (fingerprint, inline) = if git.is_null() {
+                let line = finding_line(result) as usize;
+                let source = sources.lines(&file);
+                let fingerprint =
+                    occurrences.disambiguate(secret_fingerprint(result, root, source));
+                if let Some(hash) = context_hash(source, line, line) {
+                    result["context_hash"] = json!(hash);
+                }
+                (fingerprint, Some((file.as_str(), line)))
+            } else {
+                let commit = git["commit"].as_str().unwrap_or("");
+                let source = sources.revision_lines(root, commit, &path);
+                (secret_fingerprint(result, root, source), None)
+            };
+            let target = IgnoreTarget {
+                path: &path,
+                rules: vec![result["DetectorName"].as_str().unwrap_or("")],
+                package: None,
+                fingerprint: &fingerprint,
+            };
+            if let Some(found) = Self::find_suppression(ignore_file, &mut sources, &target, inline)
+            {
+                result["suppression"] = found;
+            }
+            result["fingerprint"] = json!(fingerprint);
+        }
+    }
+
+    // `manifests` is the osv-scanner result per manifest path, annotations are looked up
+    // on the manifest line naming the package
+    pub async fn apply_sca(&self, manifests: &mut HashMap<String, Map<String, Value>>, root: &str) {
+        let ignore_file = self.ignore_file(root).await;
+        let mut sources = SourceFiles::default();
+        for (manifest, result) in manifests.iter_mut() {
+            let path = relative_path(manifest, root);
+            let packages = match result.get_mut("packages").and_then(|x| x.as_array_mut()) {
+                Some(packages) => packages,
+                None => continue,
+            };
+            for package in packages.iter_mut() {
+                let name = package["package"]["name"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                let version = package["package"]["version"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                let package_line = Regex::new(&format!(
+                    r"(^|[^\w.@/-]){}([^\w.-]|$)",
+                    regex::escape(&name)
+                ))
+                .ok()
+                .and_then(|pattern| {
+                    sources
+                        .lines(manifest)
+                        .iter()
+                        .position(|x| pattern.is_match(x))
+                })
+                .map(|x| x + 1);
+                let package_json = package.clone();
+                let vulns = match package["vulnerabilities"].as_array_mut() {
+                    Some(vulns) => vulns,
+                    None => continue,
+                };
+                for vuln in vulns.iter_mut() {
+                    let fingerprint = sca_fingerprint(manifest, &package_json, vuln, root);
+                    let mut rules = vec![vuln["id"].as_str().unwrap_or("")];
+                    if let Some(aliases) = vuln["aliases"].as_array() {
+                        rules.extend(aliases.iter().filter_map(|x| x.as_str()));
+                    }
+                    let target = IgnoreTarget {
+                        path: &path,
+                        rules,
+                        package: Some((&name, &version)),
+                        fingerprint: &fingerprint,
+                    };
+                    let found = Self::find_suppression(
+                        ignore_file,
+                        &mut sources,
+                        &target,
+                        package_line.map(|x| (manifest.as_str(), x)),
+                    );
+                    if let Some(found) = found {
+                        vuln["suppression"] = found;
+                    }
+                    vuln["fingerprint"] = json!(fingerprint);
+                }
+            }
+        }
+    }
+}
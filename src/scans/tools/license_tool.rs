@@ -1,15 +1,44 @@
 use std::{collections::HashMap, time::Instant};
 
 use mongodb::bson::uuid;
-use serde_json::json;
+use serde_json::{json, Value};
 
 use crate::{
+    license::{expression::LicenseExpr, text},
+    sbom::reader::load_sbom,
     scans::tools::sca_tool::SUPPORTED_MANIFESTS,
-    utils::{common::execute_command, file_utils::find_files_recursively},
+    utils::{
+        common::{execute_command, print_error},
+        file_utils::find_files_recursively,
+        git_auth::{clone_args, git_env},
+    },
 };
 
 pub struct LicenseTool;
 
+pub const SOURCE_FILES_KEY: &str = "source files";
+// every license file and SPDX header of the tree by path, /tmp/output.json key for notices
+pub const LICENSE_FILES_KEY: &str = "license_files";
+
+// CycloneDX license choices are either {"expression": ""} or {"license": {"id": ""}} /
+// {"license": {"name": ""}}, all of them are kept as normalized SPDX expressions
+fn declared_licenses(component: &Value) -> Vec<String> {
+    let mut licenses = Vec::new();
+    for choice in component["licenses"].as_array().unwrap_or(&vec![]) {
+        let license = choice["expression"]
+            .as_str()
+            .or_else(|| choice["license"]["id"].as_str())
+            .or_else(|| choice["license"]["name"].as_str());
+        if let Some(license) = license {
+            let license = LicenseExpr::parse_lenient(license).to_string();
+            if !licenses.contains(&license) {
+                licenses.push(license);
+            }
+        }
+    }
+    licenses
+}
+
 impl LicenseTool {
     pub fn new() -> Self {
         LicenseTool
@@ -18,8 +47,8 @@ impl LicenseTool {
     pub async fn run_scan(
         &self,
         _path: &str,
-        _commit_id: Option<&str>,
         _branch: Option<&str>,
+        pr_branch: Option<&str>,
         verbose: bool,
     ) {
         let start_time = Instant::now();
@@ -37,13 +66,16 @@ impl LicenseTool {
                 if verbose {
                     println!("[+] Cloning git repo...");
                 }
-                if let Some(_branch) = _branch {
-                    let clone_command = format!("git clone -b {} {} /tmp/app", _branch, _path);
-                    execute_command(&clone_command, false).await;
-                } else {
-                    let clone_command = format!("git clone {} /tmp/app", _path);
-                    execute_command(&clone_command, false).await;
+                let mut clone_command = std::process::Command::new("git");
+                clone_command.arg("clone").args(clone_args(false));
+                // a PR is scanned at its own branch
+                if let Some(branch) = pr_branch.or(_branch).filter(|x| !x.is_empty()) {
+                    clone_command.args(["-b", branch]);
                 }
+                let _ = clone_command
+                    .args([_path, "/tmp/app"])
+                    .envs(git_env())
+                    .output();
             } else {
                 if verbose {
                     println!("[+] Copying project to /tmp/app...");
@@ -52,23 +84,16 @@ impl LicenseTool {
                 execute_command(&copy_command, true).await;
             }
         }
-        let mut _path = format!("/tmp/app");
-        if let Some(commit_id) = _commit_id {
-            let checkout_command = format!("cd {} && git checkout {}", _path, commit_id);
-            execute_command(&checkout_command, false).await;
-
-            let copy_command = format!("mkdir -p /tmp/code");
-            execute_command(&copy_command, false).await;
-
-            let copy_command = format!("cd {} && git diff-tree --no-commit-id --name-only -r {} | xargs -I {{}} git ls-tree --name-only {} {{}} | xargs git archive --format=tar {} | tar -x -C /tmp/code", _path, commit_id, commit_id, commit_id);
-            execute_command(&copy_command, false).await;
-            // now run secret scan on /tmp/code folder
-            _path = format!("/tmp/code");
-        }
-        let manifests =
-            find_files_recursively(&_path, unsafe { SUPPORTED_MANIFESTS.to_vec() }, ignore_dirs)
-                .await;
+        let _path = format!("/tmp/app");
+        let manifests = find_files_recursively(
+            &_path,
+            unsafe { SUPPORTED_MANIFESTS.to_vec() },
+            ignore_dirs.clone(),
+        )
+        .await;
         let mut manifest_license = HashMap::new();
+        let mut manifest_sbom = HashMap::new();
+        let mut manifest_errors: HashMap<String, String> = HashMap::new();
         for manifest in manifests.iter() {
             let file_name = manifest.split("/").last().unwrap();
             let folder_path = manifest.replace(file_name, "");
@@ -83,33 +108,79 @@ impl LicenseTool {
                 );
             }
             execute_command(&license_command, false).await;
-            // Read JSON file and parse data
-            let license_json =
-                std::fs::read_to_string(format!("{}/{}", folder_path, random_file_name)).unwrap();
-            let json_data = serde_json::from_str::<serde_json::Value>(&license_json).unwrap();
+            let manifest_path = format!("{}/{}", folder_path, file_name);
+            // Read JSON file and parse data, a failing manifest shouldn't stop the scan of the others
+            let output_file = format!("{}/{}", folder_path, random_file_name);
+            let license_json = std::fs::read_to_string(&output_file);
+            let _ = std::fs::remove_file(&output_file);
+            let json_data = match license_json
+                .map_err(|_| "cdxgen did not generate an SBOM".to_string())
+                .and_then(|x| {
+                    serde_json::from_str::<Value>(&x)
+                        .map_err(|e| format!("invalid cdxgen output: {}", e))
+                }) {
+                Ok(json_data) => json_data,
+                Err(e) => {
+                    println!(
+                        "[❕] Warning: License compliance scan failed for {}: {}",
+                        manifest_path, e
+                    );
+                    manifest_errors.insert(manifest_path, e);
+                    continue;
+                }
+            };
+            // keep the complete cdxgen output around, it is used later to export the SBOM
+            manifest_sbom.insert(
+                manifest_path.clone(),
+                json!({
+                    "root": json_data["metadata"]["component"]["bom-ref"],
+                    "components": json_data["components"],
+                    "dependencies": json_data["dependencies"],
+                }),
+            );
             // extract license data from "components" key there will be list of components so grab licenses from there
-            let components = json_data["components"].as_array().unwrap();
             let mut component_licenses: HashMap<String, Vec<String>> = HashMap::new();
-            for component in components.iter() {
-                let component_name = component["name"].as_str().unwrap();
-                let component_version = component["version"].as_str().unwrap();
-                let licenses = component["licenses"].as_array().unwrap();
-                let mut license_names = Vec::new();
-                for license in licenses.iter() {
-                    let license = license["license"].as_object().unwrap();
-                    if license.contains_key("id") {
-                        license_names.push(license["id"].as_str().unwrap().to_string());
+            let mut unknown_count = 0;
+            for component in json_data["components"].as_array().unwrap_or(&vec![]) {
+                let component_name = match component["name"].as_str() {
+                    Some(component_name) => component_name,
+                    None => {
+                        println!(
+                            "[❕] Warning: skipping component without a name in {}: {}",
+                            manifest_path,
+                            component["bom-ref"].as_str().unwrap_or("no bom-ref")
+                        );
+                        continue;
+                    }
+                };
+                // local workspace packages often don't have a version
+                let package = match component["version"].as_str() {
+                    Some(version) if !version.is_empty() => {
+                        format!("{}@{}", component_name, version)
+                    }
+                    _ => component_name.to_string(),
+                };
+                // an empty list is the "unknown license" bucket, the license policy decides
+                // what happens with these packages
+                let licenses = declared_licenses(component);
+                if licenses.is_empty() {
+                    unknown_count += 1;
+                    if verbose {
+                        println!(
+                            "[❕] Warning: no license found for {} in {}",
+                            package, manifest_path
+                        );
                     }
                 }
-                component_licenses.insert(
-                    format!("{}@{}", component_name, component_version),
-                    license_names,
-                );
-                manifest_license.insert(
-                    format!("{}/{}", folder_path, file_name),
-                    component_licenses.clone(),
+                component_licenses.insert(package, licenses);
+            }
+            if unknown_count > 0 {
+                println!(
+                    "[❕] Warning: license of {} components in {} could not be determined",
+                    unknown_count, manifest_path
                 );
             }
+            manifest_license.insert(manifest_path, component_licenses);
         }
         // save data in output.json and before that get json data from output.json file if it exists and then append new data to it
         // output.json data will be in format {"sast":{}, "sca":{}, "secret":{}, "license":{}}
@@ -118,7 +189,47 @@ impl LicenseTool {
             let output_json_data = std::fs::read_to_string("/tmp/output.json").unwrap();
             output_json = serde_json::from_str::<serde_json::Value>(&output_json_data).unwrap();
         }
-        output_json["license"] = json!(manifest_license);
+        // license texts and SPDX headers catch vendored or copied code cdxgen doesn't see,
+        // they are reported like packages of a separate "source files" manifest. License
+        // files are listed by path, headers by license with the number of files so a tree
+        // full of headers doesn't flood the report.
+        let findings = text::scan_tree(&_path, &ignore_dirs);
+        let mut license_files: HashMap<String, Vec<String>> = HashMap::new();
+        let mut source_licenses: HashMap<String, Vec<String>> = HashMap::new();
+        let mut header_counts: HashMap<String, usize> = HashMap::new();
+        for finding in findings.iter() {
+            if verbose {
+                println!(
+                    "[+] Found {} license in {} ({}, confidence {})",
+                    finding.expression, finding.path, finding.source, finding.confidence
+                );
+            }
+            license_files.insert(finding.path.clone(), vec![finding.expression.clone()]);
+            if finding.source == "spdx-header" {
+                *header_counts.entry(finding.expression.clone()).or_insert(0) += 1;
+            } else {
+                source_licenses.insert(finding.path.clone(), vec![finding.expression.clone()]);
+            }
+        }
+        for (expression, count) in header_counts {
+            source_licenses.insert(
+                format!(
+                    "SPDX-License-Identifier {} ({} file{})",
+                    expression,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ),
+                vec![expression],
+            );
+        }
+        let mut output_license = json!(manifest_license);
+        if !source_licenses.is_empty() {
+            output_license[SOURCE_FILES_KEY] = json!(source_licenses);
+        }
+        output_json["license"] = output_license;
+        output_json[LICENSE_FILES_KEY] = json!(license_files);
+        output_json["license_errors"] = json!(manifest_errors);
+        output_json["sbom"] = json!(manifest_sbom);
         std::fs::write(
             "/tmp/output.json",
             serde_json::to_string_pretty(&output_json).unwrap(),
@@ -132,4 +243,53 @@ impl LicenseTool {
             elapsed_seconds
         );
     }
+
+    // Collect licenses from an existing SBOM instead of running cdxgen on the source tree
+    pub async fn run_sbom_scan(&self, sbom_path: &str, verbose: bool) {
+        let start_time = Instant::now();
+        if verbose {
+            println!("[+] Running License compliance scan on SBOM: {}", sbom_path);
+        }
+        let sbom = match load_sbom(sbom_path) {
+            Ok(sbom) => sbom,
+            Err(e) => {
+                print_error(
+                    &format!("Error: Unable to read SBOM {}: {}", sbom_path, e),
+                    101,
+                );
+                return;
+            }
+        };
+        let mut component_licenses: HashMap<String, Vec<String>> = HashMap::new();
+        for component in sbom["components"].as_array().unwrap_or(&vec![]) {
+            let component_name = match component["name"].as_str() {
+                Some(component_name) => component_name,
+                None => continue,
+            };
+            let package = match component["version"].as_str() {
+                Some(version) if !version.is_empty() => format!("{}@{}", component_name, version),
+                _ => component_name.to_string(),
+            };
+            component_licenses.insert(package, declared_licenses(component));
+        }
+
+        let mut output_json = json!({});
+        if std::path::Path::new("/tmp/output.json").exists() {
+            let output_json_data = std::fs::read_to_string("/tmp/output.json").unwrap();
+            output_json = serde_json::from_str::<serde_json::Value>(&output_json_data).unwrap();
+        }
+        output_json["license"] = json!({ sbom_path: component_licenses });
+        output_json["license_errors"] = json!({});
+        output_json["sbom"] = json!({ sbom_path: sbom });
+        std::fs::write(
+            "/tmp/output.json",
+            serde_json::to_string_pretty(&output_json).unwrap(),
+        )
+        .unwrap();
+        let elapsed_seconds = start_time.elapsed().as_secs_f64().round();
+        println!(
+            "Execution time for License Compliance scan: {:?} seconds",
+            elapsed_seconds
+        );
+    }
 }
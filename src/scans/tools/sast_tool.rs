@@ -2,7 +2,10 @@ use std::{fs, time::Instant};
 
 use serde_json::json;
 
-use crate::utils::common::{checkout, execute_command, print_error};
+use crate::{
+    scans::suppressions::Suppressions,
+    utils::common::{checkout, execute_command, print_error},
+};
 
 pub struct SastTool;
 
@@ -17,6 +20,7 @@ impl SastTool {
         base_branch: Option<&str>,
         pr_branch: Option<&str>,
         rule_path: String,
+        suppressions: &Suppressions,
         verbose: bool,
     ) {
         let start_time = Instant::now();
@@ -157,13 +161,15 @@ impl SastTool {
         let json_output =
             serde_json::from_str::<serde_json::Value>(&json_output.to_string()).unwrap();
         // pick results key from json_output
-        let json_output = json_output
+        let mut json_output = json_output
             .as_object()
             .unwrap()
             .get("results")
             .unwrap()
             .as_array()
-            .unwrap();
+            .unwrap()
+            .clone();
+        suppressions.apply_sast(&mut json_output, &_path).await;
         // save data in output.json and before that get json data from output.json file if it exists and then append new data to it
         // output.json data will be in format {"sast":{}, "sca":{}, "secret":{}, "license":{}}
         let mut output_json = json!({});
@@ -171,7 +177,7 @@ impl SastTool {
             let output_json_data = std::fs::read_to_string("/tmp/output.json").unwrap();
             output_json = serde_json::from_str::<serde_json::Value>(&output_json_data).unwrap();
         }
-        output_json["sast"] = serde_json::Value::Array(json_output.clone());
+        output_json["sast"] = serde_json::Value::Array(json_output);
 
         std::fs::write(
             "/tmp/output.json",
@@ -2,9 +2,13 @@ use std::{collections::HashMap, fs, time::Instant};
 
 use serde_json::{json, Value};
 
-use crate::utils::{
-    common::{checkout, execute_command},
-    file_utils::find_files_recursively,
+use crate::{
+    sbom::reader::{load_sbom, osv_scanner_sbom},
+    scans::suppressions::Suppressions,
+    utils::{
+        common::{checkout, execute_command, print_error},
+        file_utils::find_files_recursively,
+    },
 };
 
 pub struct ScaTool;
@@ -210,6 +214,7 @@ impl ScaTool {
         root_only: bool,
         build_args: String,
         manfiests: String,
+        suppressions: &Suppressions,
         verbose: bool,
     ) {
         let start_time = Instant::now();
@@ -374,6 +379,9 @@ impl ScaTool {
                 mainfest_sca_result.insert(format!("{}/{}", folder_path, file_name), blank_vals);
             }
         }
+        suppressions
+            .apply_sca(&mut mainfest_sca_result, &_path)
+            .await;
         // save data in output.json and before that get json data from output.json file if it exists and then append new data to it
         // output.json data will be in format {"sast":{}, "sca":{}, "secret":{}, "license":{}}
         let mut output_json = json!({});
@@ -393,4 +401,77 @@ impl ScaTool {
         let elapsed_seconds = elapsed_time.as_secs_f64().round();
         println!("Execution time for SCA scan: {:?} seconds", elapsed_seconds);
     }
+
+    // Look up vulnerabilities for the components of an existing SBOM, nothing is
+    // cloned or installed so this works without the source tree
+    pub async fn run_sbom_scan(&self, sbom_path: &str, verbose: bool) {
+        let start_time = Instant::now();
+        if verbose {
+            println!("[+] Running SCA scan on SBOM: {}", sbom_path);
+        }
+        let sbom = match load_sbom(sbom_path) {
+            Ok(sbom) => sbom,
+            Err(e) => {
+                print_error(
+                    &format!("Error: Unable to read SBOM {}: {}", sbom_path, e),
+                    101,
+                );
+                return;
+            }
+        };
+        let osv_sbom = osv_scanner_sbom(&sbom);
+        let total_components = sbom["components"].as_array().map_or(0, |x| x.len());
+        let purl_components = osv_sbom["components"].as_array().map_or(0, |x| x.len());
+        if purl_components < total_components {
+            println!(
+                "[❕] {} of {} SBOM components have no purl and can't be checked for vulnerabilities",
+                total_components - purl_components,
+                total_components
+            );
+        }
+        let _ = fs::create_dir_all("/tmp/sbom-scan");
+        fs::write(
+            "/tmp/sbom-scan/bom.cdx.json",
+            serde_json::to_string_pretty(&osv_sbom).unwrap(),
+        )
+        .unwrap();
+        let sca_output = execute_command(
+            "cd /tmp/sbom-scan && osv-scanner scan --format json -L bom.cdx.json",
+            true,
+        )
+        .await;
+        let mut sca_result = json!({
+            "source": { "path": sbom_path, "type": "sbom" },
+            "packages": [],
+        });
+        match serde_json::from_str::<Value>(&sca_output) {
+            Ok(json_output) => {
+                if let Some(result) = json_output["results"].as_array().and_then(|x| x.first()) {
+                    sca_result["packages"] = result["packages"].clone();
+                } else if verbose {
+                    println!("[*] No vulnerabilities found in {} SBOM!", sbom_path);
+                }
+            }
+            Err(_) => {
+                if verbose {
+                    println!("[*] Error while running SCA scan on {} SBOM!", sbom_path);
+                }
+            }
+        }
+
+        let mut output_json = json!({});
+        if std::path::Path::new("/tmp/output.json").exists() {
+            let output_json_data = std::fs::read_to_string("/tmp/output.json").unwrap();
+            output_json = serde_json::from_str::<serde_json::Value>(&output_json_data).unwrap();
+        }
+        output_json["sca"] = json!({ sbom_path: sca_result });
+        std::fs::write(
+            "/tmp/output.json",
+            serde_json::to_string_pretty(&output_json).unwrap(),
+        )
+        .unwrap();
+
+        let elapsed_seconds = start_time.elapsed().as_secs_f64().round();
+        println!("Execution time for SCA scan: {:?} seconds", elapsed_seconds);
+    }
 }
@@ -1,16 +1,89 @@
-use std::{fs, time::Instant};
+use std::{fs, process::Command, time::Instant};
 
 use serde_json::{json, Value};
 
-use crate::utils::common::{
-    checkout, count_env_variables, execute_command, list_whitelisted_secrets, print_error,
+use crate::{
+    scans::suppressions::Suppressions,
+    secrets::{
+        engine::SecretEngine,
+        finding_file,
+        history::{introducing_findings, scan_history, trufflehog_range_args},
+        mask_secret,
+        rules::SecretRules,
+        verify::SecretVerifier,
+    },
+    utils::common::{checkout, count_env_variables, execute_command, print_error},
 };
 
-pub struct SecretTool;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecretEngineMode {
+    // trufflehog if it is installed, the native engine otherwise
+    Auto,
+    Native,
+    Trufflehog,
+    Both,
+}
+
+impl SecretEngineMode {
+    pub fn from_name(name: &str) -> Option<SecretEngineMode> {
+        match name {
+            "auto" => Some(SecretEngineMode::Auto),
+            "native" => Some(SecretEngineMode::Native),
+            "trufflehog" => Some(SecretEngineMode::Trufflehog),
+            "both" => Some(SecretEngineMode::Both),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SecretOptions {
+    pub engine: SecretEngineMode,
+    // YAML file with additional rules for the native engine
+    pub native_rules: String,
+    // detector, value and path filters (local path or URL), bundled defaults if empty
+    pub rules: String,
+    // scan the git history instead of the working tree
+    pub history: bool,
+    // "<from>..<to>" or "<from>", the whole history if empty
+    pub commit_range: String,
+    // check whether found secrets are live with the HTTP verifiers
+    pub verify: bool,
+    // additional verifiers (local path or URL)
+    pub verifiers: String,
+}
+
+impl Default for SecretOptions {
+    fn default() -> Self {
+        SecretOptions {
+            engine: SecretEngineMode::Auto,
+            native_rules: String::new(),
+            rules: String::new(),
+            history: false,
+            commit_range: String::new(),
+            verify: false,
+            verifiers: String::new(),
+        }
+    }
+}
+
+pub struct SecretTool {
+    options: SecretOptions,
+}
+
+fn is_trufflehog_installed() -> bool {
+    Command::new("trufflehog").arg("--version").output().is_ok()
+}
 
 impl SecretTool {
     pub fn new() -> Self {
-        SecretTool
+        SecretTool {
+            options: SecretOptions::default(),
+        }
+    }
+
+    pub fn with_options(options: SecretOptions) -> Self {
+        SecretTool { options }
     }
 
     pub async fn run_scan(
@@ -18,7 +91,7 @@ impl SecretTool {
         _path: &str,
         _branch: Option<&str>,
         pr_branch: Option<&str>,
-        mongo_uri: &str,
+        suppressions: &Suppressions,
         verbose: bool,
     ) {
         let start_time = Instant::now();
@@ -63,25 +136,106 @@ impl SecretTool {
             }
         }
 
-        let cmd = format!("trufflehog filesystem --no-update {} --json --exclude-detectors=FLOAT,SIGNABLE,YANDEX,OANDA,CIRCLE,PARSEUR,URI,SENTRYTOKEN,SIRV,ETSYAPIKEY,UNIFYID,MIRO,FRESHDESK,ALIBABA,YELP,FLATIO,GETRESPONSE,ATERA,GITTER,SONARCLOUD,AZURESEARCHADMINKEY", _path);
-        let output_data = execute_command(&cmd, true).await;
-        println!("Cmd {:?}", cmd);
-        let mut results: Vec<Value> = Vec::new();
+        let secret_rules = match SecretRules::load(&self.options.rules).await {
+            Ok(secret_rules) => secret_rules,
+            Err(e) => {
+                print_error(&format!("Error: Unable to load secret rules: {}", e), 1);
+                return;
+            }
+        };
 
-        for line in output_data.lines() {
-            let json_output: serde_json::Value =
-                serde_json::from_str(&line).expect("Error parsing JSON");
+        let (use_trufflehog, use_native) = match self.options.engine {
+            SecretEngineMode::Auto => {
+                let installed = is_trufflehog_installed();
+                (installed, !installed)
+            }
+            SecretEngineMode::Native => (false, true),
+            SecretEngineMode::Trufflehog => (true, false),
+            SecretEngineMode::Both => (true, true),
+        };
+        let history = self.options.history || !self.options.commit_range.is_empty();
+        if history && !std::path::Path::new(&format!("{}/.git", _path)).exists() {
+            print_error(
+                "Error: Secret history scan needs a git repository, the project has no .git directory",
+                1,
+            );
+            return;
+        }
+        let mut raw_results: Vec<Value> = Vec::new();
+        if use_trufflehog {
+            let cmd = if history {
+                format!(
+                    "trufflehog git file://{} --no-update --json {} {}",
+                    _path,
+                    trufflehog_range_args(&self.options.commit_range),
+                    secret_rules.trufflehog_args()
+                )
+            } else {
+                format!(
+                    "trufflehog filesystem --no-update {} --json {}",
+                    _path,
+                    secret_rules.trufflehog_args()
+                )
+            };
+            let output_data = execute_command(&cmd, true).await;
+            println!("Cmd {:?}", cmd);
+            for line in output_data.lines() {
+                let json_output: serde_json::Value =
+                    serde_json::from_str(&line).expect("Error parsing JSON");
+                raw_results.push(json_output);
+            }
+        }
+        if use_native {
+            if verbose {
+                println!("[+] Running native secret engine on {}...", _path);
+            }
+            let engine = match SecretEngine::load(&self.options.native_rules) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    print_error(
+                        &format!("Error: Unable to load native secret rules: {}", e),
+                        1,
+                    );
+                    return;
+                }
+            };
+            let findings = if history {
+                if verbose {
+                    println!("[+] Scanning git history of {}...", _path);
+                }
+                match scan_history(&engine, &_path, &self.options.commit_range) {
+                    Ok(findings) => findings,
+                    Err(e) => {
+                        print_error(&format!("Error: Unable to scan git history: {}", e), 1);
+                        return;
+                    }
+                }
+            } else {
+                engine.scan_path(&_path)
+            };
+            for finding in findings {
+                // a secret found by several rules or by both engines is reported once,
+                // trufflehog's result is kept as it may carry verification data
+                let is_duplicate = raw_results.iter().any(|x| {
+                    x["Raw"] == finding["Raw"] && finding_file(x) == finding_file(&finding)
+                });
+                if !is_duplicate {
+                    raw_results.push(finding);
+                }
+            }
+        }
+        if history {
+            raw_results = introducing_findings(raw_results, &_path);
+        }
+        let mut results: Vec<Value> = Vec::new();
 
+        for json_output in raw_results {
             // if it have key SourceMetadata only then add it to results
             if json_output["SourceMetadata"].is_null() {
                 continue;
             }
             // if file path contains ".git/config"
-            if json_output["SourceMetadata"]["Data"]["Filesystem"]["file"]
-                .as_str()
-                .unwrap()
-                .contains(".git/")
-            {
+            if finding_file(&json_output).contains(".git/") {
                 println!("[+] Skipping .git/ file...");
                 continue;
             }
@@ -97,46 +251,43 @@ impl SecretTool {
             }
             results.push(json_output.clone());
         }
-        // ## iterate into each results and implement checks for specific DetectorName
+        let allowlist = suppressions.secret_allowlist().await;
         let mut new_results: Vec<Value> = Vec::new();
         for result in results.iter_mut() {
-            if result["DetectorName"].as_str().unwrap() == "JDBC" {
-                // if not contains password then continue
-                if !result["Raw"].as_str().unwrap().contains("password") {
-                    continue;
-                }
-            }
-            // if detector name is GITHUB and not starts with "gh" or "github" then continue
-            if result["DetectorName"].as_str().unwrap() == "GITHUB" {
-                if !result["Raw"].as_str().unwrap().starts_with("gh")
-                    && !result["Raw"].as_str().unwrap().starts_with("github")
-                {
-                    continue;
+            if let Some(reason) = secret_rules.skip_reason(result, &_path) {
+                if verbose {
+                    println!(
+                        "[+] Skipping {} secret in {} because {}...",
+                        result["DetectorName"].as_str().unwrap_or(""),
+                        finding_file(result),
+                        reason
+                    );
                 }
+                continue;
             }
-            // Check if the detected secret is whitelisted
-            if !mongo_uri.is_empty() {
-                // Fetch whitelisted secrets from MongoDB
-                let whitelisted_secrets = match list_whitelisted_secrets(mongo_uri).await {
-                    Ok(secrets) => secrets,
-                    Err(e) => {
-                        eprintln!("Error fetching whitelisted secrets: {}", e);
-                        continue; // You might want to handle the error differently
-                    }
-                };
-
-                // Check if the detected secret is in the whitelisted secrets
-                if let Some(raw_value) = result["Raw"].as_str() {
-                    if whitelisted_secrets.contains(&raw_value.to_string()) {
-                        println!("[+] Skipping because {} is whitelisted...", raw_value);
-                        continue;
-                    }
-                }
+            // Check if the detected secret is allowlisted
+            if let Some(entry) = allowlist.allowing_entry(result, &_path) {
+                println!(
+                    "[+] Skipping because {} is allowlisted (entry {})...",
+                    mask_secret(result["Raw"].as_str().unwrap_or("")),
+                    entry.id
+                );
+                continue;
             }
-
             new_results.push(result.clone());
         }
         results = new_results;
+        if self.options.verify || !self.options.verifiers.is_empty() {
+            let verifier = match SecretVerifier::load(&self.options.verifiers).await {
+                Ok(verifier) => verifier,
+                Err(e) => {
+                    print_error(&format!("Error: Unable to load secret verifiers: {}", e), 1);
+                    return;
+                }
+            };
+            verifier.verify_findings(&mut results, verbose).await;
+        }
+        suppressions.apply_secrets(&mut results, &_path).await;
         let json_output = serde_json::json!({
             "results": results
         });
@@ -0,0 +1,170 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use globset::{Glob, GlobMatcher};
+use rand::Rng;
+use serde_json::Value;
+
+use super::finding_file;
+use crate::{
+    store::FindingStore,
+    utils::common::{hash_text, print_error},
+};
+
+// An allowlisted secret, only a salted SHA-256 of the secret is stored so the allowlist
+// itself doesn't leak credentials. Detector and path (glob relative to the project root)
+// limit where the secret is allowed.
+#[derive(Clone, Debug)]
+pub struct AllowlistEntry {
+    pub id: String,
+    pub salt: String,
+    pub hash: String,
+    pub detector: String,
+    pub path: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub reason: String,
+    pub created_at: String,
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
+}
+
+// "2025-12-31" (valid until the end of that day) or a RFC 3339 timestamp
+pub fn parse_expiry(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(23, 59, 59).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|x| x.with_timezone(&Utc))
+        .map_err(|_| format!("invalid expiry {}, use YYYY-MM-DD or RFC 3339", value))
+}
+
+impl AllowlistEntry {
+    pub fn new(
+        secret: &str,
+        detector: &str,
+        path: &str,
+        expires_at: Option<DateTime<Utc>>,
+        reason: &str,
+    ) -> AllowlistEntry {
+        let salt = random_hex(16);
+        AllowlistEntry {
+            id: random_hex(6),
+            hash: hash_text(&format!("{}{}", salt, secret)),
+            salt,
+            detector: detector.to_string(),
+            path: path.to_string(),
+            expires_at,
+            reason: reason.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at < Utc::now(),
+            None => false,
+        }
+    }
+
+    pub fn matches_secret(&self, secret: &str) -> bool {
+        hash_text(&format!("{}{}", self.salt, secret)) == self.hash
+    }
+
+    // Matcher of the path scope, None if the entry applies to every path
+    pub fn path_matcher(&self) -> Result<Option<GlobMatcher>, String> {
+        if self.path.is_empty() {
+            return Ok(None);
+        }
+        Glob::new(&self.path)
+            .map(|x| Some(x.compile_matcher()))
+            .map_err(|e| format!("invalid path glob {}: {}", self.path, e))
+    }
+
+    // whether the entry allows `secret` found by `detector` in `relative_path`, `path` is
+    // the matcher of its path scope
+    fn allows(
+        &self,
+        path: Option<&GlobMatcher>,
+        secret: &str,
+        detector: &str,
+        relative_path: &str,
+    ) -> bool {
+        if self.is_expired() {
+            return false;
+        }
+        if !self.detector.is_empty() && !self.detector.eq_ignore_ascii_case(detector) {
+            return false;
+        }
+        if path.is_some_and(|x| !x.is_match(relative_path)) {
+            return false;
+        }
+        self.matches_secret(secret)
+    }
+}
+
+#[derive(Default)]
+pub struct Allowlist {
+    pub entries: Vec<AllowlistEntry>,
+    // index and path matcher of every entry with a valid path scope
+    matchers: Vec<(usize, Option<GlobMatcher>)>,
+}
+
+impl Allowlist {
+    // Entry allowing a finding, `root` is the scanned directory the path scope is
+    // relative to
+    pub fn allowing_entry(&self, finding: &Value, root: &str) -> Option<&AllowlistEntry> {
+        let secret = finding["Raw"].as_str()?;
+        let detector = finding["DetectorName"].as_str().unwrap_or("");
+        let file = finding_file(finding);
+        let relative_path = file
+            .strip_prefix(root)
+            .unwrap_or(file)
+            .trim_start_matches('/');
+        self.matchers
+            .iter()
+            .map(|(index, path)| (&self.entries[*index], path.as_ref()))
+            .find(|(entry, path)| entry.allows(*path, secret, detector, relative_path))
+            .map(|(entry, _)| entry)
+    }
+}
+
+// Entries with an invalid path scope allow nothing, they are reported once here
+pub async fn load_allowlist(store: &dyn FindingStore) -> Result<Allowlist, String> {
+    let entries = store.allowlist_entries().await?;
+    let mut matchers = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match entry.path_matcher() {
+            Ok(path) => matchers.push((index, path)),
+            Err(e) => print_error(
+                &format!("Warning: Skipping allowlist entry {}, {}", entry.id, e),
+                101,
+            ),
+        }
+    }
+    Ok(Allowlist { entries, matchers })
+}
+
+// Hash every entry of the plaintext whitelist into the allowlist, the plaintext entries
+// are deleted afterwards unless `keep_plaintext` is set. Returns the number of migrated
+// entries.
+pub async fn migrate_plaintext(
+    store: &dyn FindingStore,
+    dry_run: bool,
+    keep_plaintext: bool,
+) -> Result<usize, String> {
+    let plaintext = store.plaintext_secrets().await?;
+    if dry_run {
+        return Ok(plaintext.len());
+    }
+    for (id, secret) in plaintext.iter() {
+        let entry = AllowlistEntry::new(secret, "", "", None, "migrated from plaintext whitelist");
+        store.add_allowlist_entry(&entry).await?;
+        if !keep_plaintext {
+            store.remove_plaintext_secret(id).await?;
+        }
+    }
+    Ok(plaintext.len())
+}
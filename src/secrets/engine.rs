@@ -0,0 +1,185 @@
+use std::{collections::HashMap, path::Path};
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const DEFAULT_RULES: &str = include_str!("default_rules.yaml");
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RuleConfig {
+    pub id: String,
+    #[serde(default)]
+    pub detector: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub regex: String,
+    pub secret_group: Option<usize>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub entropy: Option<f64>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+pub struct Rule {
+    pub config: RuleConfig,
+    regex: Regex,
+}
+
+pub struct SecretEngine {
+    pub rules: Vec<Rule>,
+}
+
+// Shannon entropy in bits per character
+pub fn shannon_entropy(input: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for x in input.chars() {
+        *counts.entry(x).or_default() += 1;
+    }
+    let length = input.chars().count() as f64;
+    counts
+        .values()
+        .map(|x| {
+            let probability = *x as f64 / length;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+fn parse_rules(data: &str) -> Result<Vec<RuleConfig>, String> {
+    serde_yaml::from_str::<RulesFile>(data)
+        .map(|x| x.rules)
+        .map_err(|e| e.to_string())
+}
+
+impl SecretEngine {
+    // Bundled rules, plus the rules of `custom_rules_path` if given. A custom rule with
+    // the id of a bundled rule replaces it, `disabled: true` turns a bundled rule off.
+    pub fn load(custom_rules_path: &str) -> Result<SecretEngine, String> {
+        let mut configs = parse_rules(DEFAULT_RULES)?;
+        if !custom_rules_path.is_empty() {
+            let data = std::fs::read_to_string(custom_rules_path)
+                .map_err(|e| format!("unable to read {}: {}", custom_rules_path, e))?;
+            for config in parse_rules(&data)? {
+                configs.retain(|x| x.id != config.id);
+                configs.push(config);
+            }
+        }
+        let mut rules = Vec::new();
+        for mut config in configs.into_iter().filter(|x| !x.disabled) {
+            let regex = Regex::new(&config.regex)
+                .map_err(|e| format!("invalid regex of secret rule {}: {}", config.id, e))?;
+            if config.detector.is_empty() {
+                config.detector = config.id.clone();
+            }
+            config.keywords = config.keywords.iter().map(|x| x.to_lowercase()).collect();
+            rules.push(Rule { config, regex });
+        }
+        Ok(SecretEngine { rules })
+    }
+
+    // Findings in trufflehog's JSON format, so they can go through the same filters and
+    // reports as trufflehog results
+    pub fn scan_text(&self, file: &str, text: &str) -> Vec<Value> {
+        let lowercase = text.to_lowercase();
+        let mut findings = Vec::new();
+        for rule in self.rules.iter() {
+            if !rule.config.keywords.is_empty()
+                && !rule.config.keywords.iter().any(|x| lowercase.contains(x))
+            {
+                continue;
+            }
+            let group = rule
+                .config
+                .secret_group
+                .unwrap_or(if rule.regex.captures_len() > 1 { 1 } else { 0 });
+            for captures in rule.regex.captures_iter(text) {
+                let secret = match captures.get(group) {
+                    Some(secret) => secret,
+                    None => continue,
+                };
+                if let Some(min_entropy) = rule.config.entropy {
+                    if shannon_entropy(secret.as_str()) < min_entropy {
+                        continue;
+                    }
+                }
+                let line = text[..secret.start()].matches('\n').count() + 1;
+                findings.push(json!({
+                    "SourceMetadata": {
+                        "Data": {
+                            "Filesystem": {
+                                "file": file,
+                                "line": line,
+                            }
+                        }
+                    },
+                    "SourceName": "hela-native",
+                    "DetectorName": rule.config.detector,
+                    "DecoderName": "PLAIN",
+                    "Verified": false,
+                    "Raw": secret.as_str(),
+                    "RawV2": "",
+                    "Redacted": "",
+                    "ExtraData": {
+                        "rule_id": rule.config.id,
+                        "description": rule.config.description,
+                    },
+                }));
+            }
+        }
+        findings
+    }
+
+    pub fn scan_path(&self, root: &str) -> Vec<Value> {
+        let mut files = Vec::new();
+        collect_files(Path::new(root), &mut files);
+        files.sort();
+        let mut findings = Vec::new();
+        for file in files.iter() {
+            let size = std::fs::metadata(file).map(|x| x.len()).unwrap_or(0);
+            if size == 0 || size > MAX_FILE_SIZE {
+                continue;
+            }
+            let data = match std::fs::read(file) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            // binary files are skipped
+            if data.iter().take(8000).any(|x| *x == 0) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&data);
+            findings.extend(self.scan_text(&file.to_string_lossy(), &text));
+        }
+        findings
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            if entry.file_name() != ".git" {
+                collect_files(&entry.path(), files);
+            }
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, process::Command};
+
+use chrono::DateTime;
+use serde_json::{json, Value};
+
+use super::{engine::SecretEngine, finding_file};
+
+// same timestamp format trufflehog uses for git findings
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+const COMMIT_MARKER: &str = "\u{1}commit ";
+
+struct Commit {
+    hash: String,
+    author: String,
+    timestamp: String,
+}
+
+// `range` is "<from>..<to>", "<from>" for <from>..HEAD, or empty for the history of all refs
+fn log_range_args(range: &str) -> Vec<String> {
+    if range.is_empty() {
+        vec!["--all".to_string()]
+    } else if range.contains("..") {
+        vec![range.to_string()]
+    } else {
+        vec![format!("{}..HEAD", range)]
+    }
+}
+
+pub fn trufflehog_range_args(range: &str) -> String {
+    let (from, to) = range.split_once("..").unwrap_or((range, ""));
+    let mut args = Vec::new();
+    if !from.is_empty() {
+        args.push(format!("--since-commit={}", from));
+    }
+    if !to.is_empty() {
+        args.push(format!("--branch={}", to));
+    }
+    args.join(" ")
+}
+
+// start line of the new file in a hunk header like "@@ -12,0 +13,2 @@"
+fn hunk_start(header: &str) -> usize {
+    header
+        .split_whitespace()
+        .find_map(|x| x.strip_prefix('+'))
+        .and_then(|x| x.split(',').next())
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(1)
+}
+
+fn scan_added_lines(
+    engine: &SecretEngine,
+    commit: &Commit,
+    file: &str,
+    added: &[(usize, String)],
+    findings: &mut Vec<Value>,
+) {
+    if file.is_empty() || added.is_empty() {
+        return;
+    }
+    let text = added
+        .iter()
+        .map(|(_, x)| x.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    for mut finding in engine.scan_text(file, &text) {
+        let index = finding["SourceMetadata"]["Data"]["Filesystem"]["line"]
+            .as_u64()
+            .unwrap_or(1) as usize;
+        let line = added.get(index - 1).map(|(x, _)| *x).unwrap_or(0);
+        finding["SourceMetadata"] = json!({
+            "Data": {
+                "Git": {
+                    "commit": commit.hash,
+                    "file": file,
+                    "email": commit.author,
+                    "timestamp": commit.timestamp,
+                    "line": line,
+                }
+            }
+        });
+        findings.push(finding);
+    }
+}
+
+// Run the native engine over the lines added by every commit of `range`, findings are in
+// the format of trufflehog's git source
+pub fn scan_history(engine: &SecretEngine, repo: &str, range: &str) -> Result<Vec<Value>, String> {
+    let mut args = vec![
+        "-c".to_string(),
+        "core.quotePath=false".to_string(),
+        "log".to_string(),
+        "--reverse".to_string(),
+        "-p".to_string(),
+        "-U0".to_string(),
+        "--no-color".to_string(),
+        "--no-ext-diff".to_string(),
+        format!("--format={}%H%x1f%an <%ae>%x1f%ad", COMMIT_MARKER),
+        format!("--date=format:{}", TIMESTAMP_FORMAT),
+    ];
+    args.extend(log_range_args(range));
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(repo)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let log = String::from_utf8_lossy(&output.stdout);
+
+    let mut findings = Vec::new();
+    let mut commit: Option<Commit> = None;
+    let mut file = String::new();
+    let mut added: Vec<(usize, String)> = Vec::new();
+    let mut in_file_header = false;
+    let mut next_line = 1;
+    for line in log.lines() {
+        if let Some(header) = line.strip_prefix(COMMIT_MARKER) {
+            if let Some(commit) = &commit {
+                scan_added_lines(engine, commit, &file, &added, &mut findings);
+            }
+            added.clear();
+            file.clear();
+            let parts = header.splitn(3, '\u{1f}').collect::<Vec<&str>>();
+            commit = Some(Commit {
+                hash: parts[0].to_string(),
+                author: parts.get(1).unwrap_or(&"").to_string(),
+                timestamp: parts.get(2).unwrap_or(&"").to_string(),
+            });
+        } else if line.starts_with("diff --git ") {
+            if let Some(commit) = &commit {
+                scan_added_lines(engine, commit, &file, &added, &mut findings);
+            }
+            added.clear();
+            file.clear();
+            in_file_header = true;
+        } else if in_file_header && line.starts_with("+++ ") {
+            let path = line[4..].trim_end_matches('\t');
+            file = match path.strip_prefix("b/") {
+                Some(path) => path.to_string(),
+                // deleted file
+                None => String::new(),
+            };
+        } else if line.starts_with("@@ ") {
+            in_file_header = false;
+            next_line = hunk_start(line);
+        } else if let Some(text) = line.strip_prefix('+') {
+            if !in_file_header {
+                added.push((next_line, text.to_string()));
+                next_line += 1;
+            }
+        }
+    }
+    if let Some(commit) = &commit {
+        scan_added_lines(engine, commit, &file, &added, &mut findings);
+    }
+    Ok(findings)
+}
+
+fn commit_time(finding: &Value) -> i64 {
+    let timestamp = finding["SourceMetadata"]["Data"]["Git"]["timestamp"]
+        .as_str()
+        .unwrap_or("");
+    DateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .map(|x| x.timestamp())
+        .unwrap_or(i64::MAX)
+}
+
+// git grep works line by line, so a multi-line secret like a private key is looked up by
+// its longest line
+fn is_present_at_head(repo: &str, raw: &str) -> bool {
+    let needle = match raw.lines().map(|x| x.trim()).max_by_key(|x| x.len()) {
+        Some(needle) if !needle.is_empty() => needle,
+        _ => return false,
+    };
+    Command::new("git")
+        .args(["grep", "-q", "-F", "-e", needle, "HEAD", "--"])
+        .current_dir(repo)
+        .output()
+        .map(|x| x.status.success())
+        .unwrap_or(false)
+}
+
+// A secret shows up in every commit which touches it, only the commit which introduced it
+// into a file is reported, with `present_at_head` telling whether it was removed since
+pub fn introducing_findings(findings: Vec<Value>, repo: &str) -> Vec<Value> {
+    let mut introduced: Vec<Value> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    for finding in findings {
+        let key = (
+            finding_file(&finding).to_string(),
+            finding["Raw"].as_str().unwrap_or("").to_string(),
+        );
+        match index.get(&key) {
+            Some(position) => {
+                if commit_time(&finding) < commit_time(&introduced[*position]) {
+                    introduced[*position] = finding;
+                }
+            }
+            None => {
+                index.insert(key, introduced.len());
+                introduced.push(finding);
+            }
+        }
+    }
+    for finding in introduced.iter_mut() {
+        let present = is_present_at_head(repo, finding["Raw"].as_str().unwrap_or(""));
+        finding["SourceMetadata"]["Data"]["Git"]["present_at_head"] = json!(present);
+    }
+    introduced
+}
@@ -0,0 +1,73 @@
+pub mod allowlist;
+pub mod engine;
+pub mod history;
+pub mod rules;
+pub mod verify;
+
+use serde_json::Value;
+
+use crate::utils::common::hash_text;
+
+// Secret findings use trufflehog's JSON format, working tree scans keep the location under
+// SourceMetadata.Data.Filesystem and git history scans under SourceMetadata.Data.Git.
+
+pub fn finding_file(finding: &Value) -> &str {
+    let data = &finding["SourceMetadata"]["Data"];
+    data["Filesystem"]["file"]
+        .as_str()
+        .or_else(|| data["Git"]["file"].as_str())
+        .unwrap_or("")
+}
+
+pub fn finding_line(finding: &Value) -> i64 {
+    let data = &finding["SourceMetadata"]["Data"];
+    data["Filesystem"]["line"]
+        .as_i64()
+        .or_else(|| data["Git"]["line"].as_i64())
+        .unwrap_or(0)
+}
+
+// Content of a config file given as a local path or an http(s) URL
+pub async fn read_config(location: &str) -> Result<String, String> {
+    if location.starts_with("http") {
+        let response = reqwest::get(location)
+            .await
+            .map_err(|e| format!("unable to fetch {}: {}", location, e))?;
+        return response
+            .text()
+            .await
+            .map_err(|e| format!("unable to fetch {}: {}", location, e));
+    }
+    std::fs::read_to_string(location).map_err(|e| format!("unable to read {}: {}", location, e))
+}
+
+// A few characters of both ends of the secret and a hash of it, enough to recognise a
+// secret and tell secrets apart without revealing them, e.g. "ghp_****xY5a (sha256:1a2b3c4d5e6f)"
+pub fn mask_secret(raw: &str) -> String {
+    let chars = raw.trim().chars().collect::<Vec<char>>();
+    // short secrets get fewer (or no) visible characters
+    let visible = (chars.len() / 8).min(4);
+    let hash = &hash_text(raw)[..12];
+    if visible == 0 {
+        return format!("**** (sha256:{})", hash);
+    }
+    format!(
+        "{}****{} (sha256:{})",
+        chars[..visible].iter().collect::<String>(),
+        chars[chars.len() - visible..].iter().collect::<String>(),
+        hash
+    )
+}
+
+// Mask the secret values of the secret results in a /tmp/output.json document
+pub fn mask_output(output_json: &mut Value) {
+    if let Some(results) = output_json["secret"]["results"].as_array_mut() {
+        for result in results.iter_mut() {
+            for key in ["Raw", "RawV2"] {
+                if let Some(raw) = result[key].as_str().filter(|x| !x.is_empty()) {
+                    result[key] = Value::String(mask_secret(raw));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{finding_file, read_config};
+
+const DEFAULT_SECRET_RULES: &str = include_str!("default_secret_rules.yaml");
+const ANY_DETECTOR: &str = "*";
+
+#[derive(Debug, Default, Deserialize)]
+struct IncludeExclude {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawFilterConfig {
+    #[serde(default)]
+    must_match: Vec<String>,
+    #[serde(default)]
+    must_not_match: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SecretRulesFile {
+    #[serde(default)]
+    detectors: IncludeExclude,
+    #[serde(default)]
+    filters: HashMap<String, RawFilterConfig>,
+    #[serde(default)]
+    paths: IncludeExclude,
+}
+
+struct RawFilter {
+    // lowercase detector name or "*"
+    detector: String,
+    must_match: Vec<Regex>,
+    must_not_match: Vec<Regex>,
+}
+
+// Declarative noise filters for secret findings: which detectors run, what the Raw value
+// of a detector's findings must (not) look like and which paths are scanned
+pub struct SecretRules {
+    include_detectors: Vec<String>,
+    exclude_detectors: Vec<String>,
+    filters: Vec<RawFilter>,
+    include_paths: Option<GlobSet>,
+    exclude_paths: GlobSet,
+}
+
+fn compile_regexes(patterns: &[String], detector: &str) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|x| {
+            Regex::new(x)
+                .map_err(|e| format!("invalid filter regex of detector {}: {}", detector, e))
+        })
+        .collect()
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter() {
+        let glob =
+            Glob::new(pattern).map_err(|e| format!("invalid path glob {}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn lowercase(values: &[String]) -> Vec<String> {
+    values.iter().map(|x| x.to_lowercase()).collect()
+}
+
+impl SecretRules {
+    pub fn from_yaml(data: &str) -> Result<SecretRules, String> {
+        let config: SecretRulesFile = serde_yaml::from_str(data).map_err(|e| e.to_string())?;
+        let mut filters = Vec::new();
+        for (detector, filter) in config.filters.iter() {
+            filters.push(RawFilter {
+                detector: detector.to_lowercase(),
+                must_match: compile_regexes(&filter.must_match, detector)?,
+                must_not_match: compile_regexes(&filter.must_not_match, detector)?,
+            });
+        }
+        let include_paths = if config.paths.include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(&config.paths.include)?)
+        };
+        Ok(SecretRules {
+            include_detectors: lowercase(&config.detectors.include),
+            exclude_detectors: lowercase(&config.detectors.exclude),
+            filters,
+            include_paths,
+            exclude_paths: build_glob_set(&config.paths.exclude)?,
+        })
+    }
+
+    // `location` is a local path or an http(s) URL, the bundled defaults are used if empty
+    pub async fn load(location: &str) -> Result<SecretRules, String> {
+        if location.is_empty() {
+            return SecretRules::from_yaml(DEFAULT_SECRET_RULES);
+        }
+        SecretRules::from_yaml(&read_config(location).await?)
+    }
+
+    // detector selection flags for trufflehog, so excluded detectors don't run at all
+    pub fn trufflehog_args(&self) -> String {
+        let mut args = Vec::new();
+        if !self.include_detectors.is_empty() {
+            args.push(format!(
+                "--include-detectors={}",
+                self.include_detectors.join(",").to_uppercase()
+            ));
+        }
+        if !self.exclude_detectors.is_empty() {
+            args.push(format!(
+                "--exclude-detectors={}",
+                self.exclude_detectors.join(",").to_uppercase()
+            ));
+        }
+        args.join(" ")
+    }
+
+    pub fn is_detector_enabled(&self, detector: &str) -> bool {
+        let detector = detector.to_lowercase();
+        if !self.include_detectors.is_empty() && !self.include_detectors.contains(&detector) {
+            return false;
+        }
+        !self.exclude_detectors.contains(&detector)
+    }
+
+    pub fn is_path_enabled(&self, relative_path: &str) -> bool {
+        if let Some(include_paths) = &self.include_paths {
+            if !include_paths.is_match(relative_path) {
+                return false;
+            }
+        }
+        !self.exclude_paths.is_match(relative_path)
+    }
+
+    // Reason why a finding is filtered out, None if it should be reported. `root` is the
+    // scanned directory, path globs are matched against paths relative to it.
+    pub fn skip_reason(&self, finding: &Value, root: &str) -> Option<String> {
+        let detector = finding["DetectorName"].as_str().unwrap_or("");
+        if !self.is_detector_enabled(detector) {
+            return Some(format!("detector {} is disabled", detector));
+        }
+        let file = finding_file(finding);
+        let relative_path = file
+            .strip_prefix(root)
+            .unwrap_or(file)
+            .trim_start_matches('/');
+        if !self.is_path_enabled(relative_path) {
+            return Some(format!("path {} is excluded", relative_path));
+        }
+        let raw = finding["Raw"].as_str().unwrap_or("");
+        let detector = detector.to_lowercase();
+        for filter in self
+            .filters
+            .iter()
+            .filter(|x| x.detector == detector || x.detector == ANY_DETECTOR)
+        {
+            if let Some(regex) = filter.must_match.iter().find(|x| !x.is_match(raw)) {
+                return Some(format!("value doesn't match {}", regex.as_str()));
+            }
+            if let Some(regex) = filter.must_not_match.iter().find(|x| x.is_match(raw)) {
+                return Some(format!("value matches {}", regex.as_str()));
+            }
+        }
+        None
+    }
+}
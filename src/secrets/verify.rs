@@ -0,0 +1,318 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{finding_file, read_config};
+
+const DEFAULT_VERIFIERS: &str = include_str!("default_verifiers.yaml");
+const SECRET_PLACEHOLDER: &str = "{secret}";
+
+#[async_trait]
+pub trait Verifier: Send + Sync {
+    // detector names this verifier checks, matched case-insensitively
+    fn detectors(&self) -> Vec<String>;
+
+    // Ok(true) if the secret is live, Ok(false) if it was rejected and Err if the
+    // verifier couldn't tell
+    async fn verify(&self, secret: &str) -> Result<bool, String>;
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_verified_status() -> Vec<u16> {
+    vec![200]
+}
+
+fn default_unverified_status() -> Vec<u16> {
+    vec![401, 403]
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HttpVerifierConfig {
+    pub detector: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default = "default_verified_status")]
+    pub verified_status: Vec<u16>,
+    #[serde(default = "default_unverified_status")]
+    pub unverified_status: Vec<u16>,
+    // regex the response body of a verified status needs to match as well
+    pub verified_body: Option<String>,
+    // seconds
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifiersFile {
+    #[serde(default)]
+    verifiers: Vec<HttpVerifierConfig>,
+}
+
+pub struct HttpVerifier {
+    config: HttpVerifierConfig,
+    method: reqwest::Method,
+    verified_body: Option<Regex>,
+    client: reqwest::Client,
+}
+
+impl HttpVerifier {
+    pub fn new(config: HttpVerifierConfig) -> Result<HttpVerifier, String> {
+        if config.url.is_empty() {
+            return Err(format!("verifier of {} has no url", config.detector));
+        }
+        let method =
+            reqwest::Method::from_bytes(config.method.to_uppercase().as_bytes()).map_err(|_| {
+                format!(
+                    "invalid method {} of verifier {}",
+                    config.method, config.detector
+                )
+            })?;
+        let verified_body = match &config.verified_body {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| {
+                format!(
+                    "invalid verified_body of verifier {}: {}",
+                    config.detector, e
+                )
+            })?),
+            None => None,
+        };
+        Ok(HttpVerifier {
+            config,
+            method,
+            verified_body,
+            client: reqwest::Client::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl Verifier for HttpVerifier {
+    fn detectors(&self) -> Vec<String> {
+        vec![self.config.detector.clone()]
+    }
+
+    async fn verify(&self, secret: &str) -> Result<bool, String> {
+        let substitute = |x: &str| x.replace(SECRET_PLACEHOLDER, secret);
+        let mut request = self
+            .client
+            .request(self.method.clone(), substitute(&self.config.url))
+            .timeout(Duration::from_secs(self.config.timeout));
+        for (name, value) in self.config.headers.iter() {
+            request = request.header(name, substitute(value));
+        }
+        if !self.config.body.is_empty() {
+            request = request.body(substitute(&self.config.body));
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        if self.config.unverified_status.contains(&status) {
+            return Ok(false);
+        }
+        if !self.config.verified_status.contains(&status) {
+            return Err(format!("unexpected response status {}", status));
+        }
+        match &self.verified_body {
+            Some(verified_body) => {
+                let body = response.text().await.map_err(|e| e.to_string())?;
+                Ok(verified_body.is_match(&body))
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+pub struct SecretVerifier {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl SecretVerifier {
+    pub fn new(verifiers: Vec<Box<dyn Verifier>>) -> SecretVerifier {
+        SecretVerifier { verifiers }
+    }
+
+    // Bundled HTTP verifiers plus the ones of `location` (local path or URL), a custom
+    // verifier replaces the bundled one of the same detector
+    pub async fn load(location: &str) -> Result<SecretVerifier, String> {
+        let parse = |data: &str| {
+            serde_yaml::from_str::<VerifiersFile>(data)
+                .map(|x| x.verifiers)
+                .map_err(|e| e.to_string())
+        };
+        let mut configs = parse(DEFAULT_VERIFIERS)?;
+        if !location.is_empty() {
+            for config in parse(&read_config(location).await?)? {
+                configs.retain(|x| !x.detector.eq_ignore_ascii_case(&config.detector));
+                configs.push(config);
+            }
+        }
+        let mut verifiers: Vec<Box<dyn Verifier>> = Vec::new();
+        for config in configs.into_iter().filter(|x| !x.disabled) {
+            verifiers.push(Box::new(HttpVerifier::new(config)?));
+        }
+        Ok(SecretVerifier::new(verifiers))
+    }
+
+    fn verifier(&self, detector: &str) -> Option<&dyn Verifier> {
+        self.verifiers
+            .iter()
+            .find(|x| {
+                x.detectors()
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(detector))
+            })
+            .map(|x| x.as_ref())
+    }
+
+    // Sets `Verified` of every finding a verifier exists for, findings trufflehog already
+    // verified are kept. Each secret is only sent to its verifier once.
+    pub async fn verify_findings(&self, findings: &mut [Value], verbose: bool) {
+        let mut checked: HashMap<(String, String), Result<bool, String>> = HashMap::new();
+        for finding in findings.iter_mut() {
+            if finding["Verified"] == true {
+                continue;
+            }
+            let detector = finding["DetectorName"]
+                .as_str()
+                .unwrap_or("")
+                .to_lowercase();
+            let verifier = match self.verifier(&detector) {
+                Some(verifier) => verifier,
+                None => continue,
+            };
+            let raw = finding["Raw"].as_str().unwrap_or("").to_string();
+            let key = (detector.clone(), raw.clone());
+            if !checked.contains_key(&key) {
+                if verbose {
+                    println!(
+                        "[+] Verifying {} secret in {}...",
+                        finding["DetectorName"].as_str().unwrap_or(""),
+                        finding_file(finding)
+                    );
+                }
+                checked.insert(key.clone(), verifier.verify(&raw).await);
+            }
+            match &checked[&key] {
+                Ok(verified) => {
+                    finding["Verified"] = json!(verified);
+                }
+                Err(e) => {
+                    finding["Verified"] = json!(false);
+                    finding["VerificationError"] = json!(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    // Answers every request with `status` and `body` after `delay`, returns the verifier
+    // url of the listener
+    fn serve(status: u16, body: &'static str, delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer);
+                thread::sleep(delay);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        format!("http://{}/check?key={}", address, SECRET_PLACEHOLDER)
+    }
+
+    fn config(url: String) -> HttpVerifierConfig {
+        serde_yaml::from_str(&format!("detector: Test\nurl: '{}'", url)).unwrap()
+    }
+
+    async fn verify(config: HttpVerifierConfig) -> Value {
+        let verifier = SecretVerifier::new(vec![Box::new(HttpVerifier::new(config).unwrap())]);
+        let mut findings =
+            vec![json!({"DetectorName": "TEST", "Raw": "secret", "Verified": false})];
+        verifier.verify_findings(&mut findings, false).await;
+        findings.remove(0)
+    }
+
+    #[tokio::test]
+    async fn verified_status_marks_finding_verified() {
+        let finding = verify(config(serve(200, "", Duration::ZERO))).await;
+        assert_eq!(finding["Verified"], true);
+        assert!(finding.get("VerificationError").is_none());
+    }
+
+    #[tokio::test]
+    async fn unverified_status_marks_finding_unverified() {
+        let finding = verify(config(serve(401, "", Duration::ZERO))).await;
+        assert_eq!(finding["Verified"], false);
+        assert!(finding.get("VerificationError").is_none());
+    }
+
+    #[tokio::test]
+    async fn verified_body_has_to_match() {
+        let mut matching = config(serve(200, r#"{"ok": true}"#, Duration::ZERO));
+        matching.verified_body = Some(r#""ok":\s*true"#.to_string());
+        assert_eq!(verify(matching).await["Verified"], true);
+
+        let mut other = config(serve(200, r#"{"ok": false}"#, Duration::ZERO));
+        other.verified_body = Some(r#""ok":\s*true"#.to_string());
+        let finding = verify(other).await;
+        assert_eq!(finding["Verified"], false);
+        assert!(finding.get("VerificationError").is_none());
+    }
+
+    #[tokio::test]
+    async fn unexpected_status_is_a_verification_error() {
+        let finding = verify(config(serve(500, "", Duration::ZERO))).await;
+        assert_eq!(finding["Verified"], false);
+        assert_eq!(
+            finding["VerificationError"],
+            "unexpected response status 500"
+        );
+    }
+
+    #[tokio::test]
+    async fn timeout_is_a_verification_error() {
+        let mut slow = config(serve(200, "", Duration::from_secs(3)));
+        slow.timeout = 1;
+        let finding = verify(slow).await;
+        assert_eq!(finding["Verified"], false);
+        assert!(finding["VerificationError"].is_string());
+    }
+}
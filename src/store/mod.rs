@@ -0,0 +1,134 @@
+pub mod mongo;
+pub mod sqlite;
+
+use std::{collections::HashSet, sync::Arc};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::secrets::allowlist::AllowlistEntry;
+
+pub const DEFAULT_DATABASE: &str = "code-security-open-source";
+
+// Database and collection (table for SQLite) names of a store
+#[derive(Clone, Debug)]
+pub struct StoreConfig {
+    pub database: String,
+    pub hashes: String,
+    pub allowlist: String,
+    // plaintext secret whitelist of older Hela versions, only read by the migration
+    pub plaintext_secrets: String,
+    pub jobs: String,
+    pub triage: String,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            database: DEFAULT_DATABASE.to_string(),
+            hashes: "hashes".to_string(),
+            allowlist: "secret_allowlist".to_string(),
+            plaintext_secrets: "secrets".to_string(),
+            jobs: "jobs".to_string(),
+            triage: "triage".to_string(),
+        }
+    }
+}
+
+impl StoreConfig {
+    // `collections` overrides the default names, e.g. "hashes=hela_hashes,jobs=hela_jobs"
+    pub fn new(database: &str, collections: &str) -> Result<StoreConfig, String> {
+        let mut config = StoreConfig::default();
+        if !database.is_empty() {
+            config.database = database.to_string();
+        }
+        for item in collections.split(',').filter(|x| !x.trim().is_empty()) {
+            let (kind, name) = match item.split_once('=') {
+                Some((kind, name)) if !name.trim().is_empty() => (kind.trim(), name.trim()),
+                _ => return Err(format!("invalid collection {}, use <kind>=<name>", item)),
+            };
+            let field = match kind {
+                "hashes" => &mut config.hashes,
+                "allowlist" => &mut config.allowlist,
+                "secrets" => &mut config.plaintext_secrets,
+                "jobs" => &mut config.jobs,
+                "triage" => &mut config.triage,
+                _ => {
+                    return Err(format!(
+                        "unknown collection {}, use hashes, allowlist, secrets, jobs or triage",
+                        kind
+                    ))
+                }
+            };
+            *field = name.to_string();
+        }
+        Ok(config)
+    }
+}
+
+// A finding remembered as known, tagged with where it was recorded
+#[derive(Clone, Debug, Default)]
+pub struct FindingRecord {
+    pub hash: String,
+    pub repo: String,
+    pub branch: String,
+    pub job_id: String,
+}
+
+// Triage state of a finding hash
+#[derive(Clone, Debug, Default)]
+pub struct TriageRecord {
+    pub hash: String,
+    pub state: String,
+    pub justification: String,
+    pub owner: String,
+    // RFC 3339, empty if the state doesn't expire
+    pub expires_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct JobRecord {
+    pub job_id: String,
+    pub message: String,
+    pub status: i32,
+    pub results: Vec<Value>,
+    pub created_at: String,
+}
+
+// Persistence of everything Hela remembers between runs
+#[async_trait]
+pub trait FindingStore: Send + Sync {
+    // finding hashes of earlier runs
+    async fn known_hashes(&self, hashes: &[String]) -> Result<HashSet<String>, String>;
+    async fn register_finding(&self, record: &FindingRecord) -> Result<(), String>;
+
+    async fn allowlist_entries(&self) -> Result<Vec<AllowlistEntry>, String>;
+    async fn add_allowlist_entry(&self, entry: &AllowlistEntry) -> Result<(), String>;
+    // returns whether the entry existed
+    async fn remove_allowlist_entry(&self, id: &str) -> Result<bool, String>;
+    // id and secret of every plaintext whitelist entry
+    async fn plaintext_secrets(&self) -> Result<Vec<(String, String)>, String>;
+    async fn remove_plaintext_secret(&self, id: &str) -> Result<(), String>;
+
+    async fn insert_job(&self, job: &JobRecord) -> Result<(), String>;
+
+    // records of the given hashes, all records for None
+    async fn triage_records(&self, hashes: Option<&[String]>) -> Result<Vec<TriageRecord>, String>;
+    async fn set_triage_record(&self, record: &TriageRecord) -> Result<(), String>;
+}
+
+// The backend is selected by the scheme of `uri`: mongodb:// or mongodb+srv:// for
+// MongoDB, sqlite://<path> for a SQLite file
+pub async fn open(uri: &str, config: &StoreConfig) -> Result<Arc<dyn FindingStore>, String> {
+    if uri.starts_with("mongodb://") || uri.starts_with("mongodb+srv://") {
+        return Ok(Arc::new(mongo::MongoStore::new(uri, config.clone()).await?));
+    }
+    if let Some(path) = uri.strip_prefix("sqlite://") {
+        return Ok(Arc::new(sqlite::SqliteStore::open(path, config.clone())?));
+    }
+    Err(format!(
+        "unsupported store {}, use mongodb://, mongodb+srv:// or sqlite://",
+        uri
+    ))
+}
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use bson::{oid::ObjectId, to_bson, Bson};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::{ReplaceOptions, UpdateOptions},
+    Client, Collection,
+};
+
+use super::{FindingRecord, FindingStore, JobRecord, StoreConfig, TriageRecord};
+use crate::{secrets::allowlist::AllowlistEntry, utils::common::connect_to_mongodb};
+
+pub struct MongoStore {
+    client: Client,
+    config: StoreConfig,
+}
+
+fn allowlist_document(entry: &AllowlistEntry) -> Document {
+    doc! {
+        "entry_id": &entry.id,
+        "salt": &entry.salt,
+        "hash": &entry.hash,
+        "detector": &entry.detector,
+        "path": &entry.path,
+        "expires_at": entry.expires_at.map(|x| x.to_rfc3339()).unwrap_or_default(),
+        "reason": &entry.reason,
+        "created_at": &entry.created_at,
+    }
+}
+
+fn allowlist_entry(document: &Document) -> Option<AllowlistEntry> {
+    let get = |key: &str| document.get_str(key).unwrap_or("").to_string();
+    if get("salt").is_empty() || get("hash").is_empty() {
+        return None;
+    }
+    let expires_at = match document.get_str("expires_at") {
+        Ok(value) if !value.is_empty() => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|x| x.with_timezone(&Utc)),
+        _ => None,
+    };
+    Some(AllowlistEntry {
+        id: get("entry_id"),
+        salt: get("salt"),
+        hash: get("hash"),
+        detector: get("detector"),
+        path: get("path"),
+        expires_at,
+        reason: get("reason"),
+        created_at: get("created_at"),
+    })
+}
+
+impl MongoStore {
+    pub async fn new(uri: &str, config: StoreConfig) -> Result<MongoStore, String> {
+        let client = connect_to_mongodb(uri, &config.database)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MongoStore { client, config })
+    }
+
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.client.database(&self.config.database).collection(name)
+    }
+
+    async fn find(&self, name: &str, filter: Document) -> Result<Vec<Document>, String> {
+        let mut cursor = self
+            .collection(name)
+            .find(filter, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut documents = Vec::new();
+        while let Some(document) = cursor.next().await {
+            documents.push(document.map_err(|e| e.to_string())?);
+        }
+        Ok(documents)
+    }
+
+    async fn insert(&self, name: &str, document: Document) -> Result<(), String> {
+        self.collection(name)
+            .insert_one(document, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl FindingStore for MongoStore {
+    async fn known_hashes(&self, hashes: &[String]) -> Result<HashSet<String>, String> {
+        let documents = self
+            .find(&self.config.hashes, doc! { "hash": { "$in": hashes } })
+            .await?;
+        Ok(documents
+            .iter()
+            .filter_map(|x| x.get_str("hash").ok())
+            .map(|x| x.to_string())
+            .collect())
+    }
+
+    async fn register_finding(&self, record: &FindingRecord) -> Result<(), String> {
+        // Keep the first registration of a hash, like INSERT OR IGNORE in sqlite
+        self.collection(&self.config.hashes)
+            .update_one(
+                doc! { "hash": &record.hash },
+                doc! {
+                    "$setOnInsert": {
+                        "repo": &record.repo,
+                        "branch": &record.branch,
+                        "job_id": &record.job_id,
+                        "created_at": Utc::now().to_rfc3339(),
+                    }
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn allowlist_entries(&self) -> Result<Vec<AllowlistEntry>, String> {
+        let documents = self.find(&self.config.allowlist, doc! {}).await?;
+        Ok(documents.iter().filter_map(allowlist_entry).collect())
+    }
+
+    async fn add_allowlist_entry(&self, entry: &AllowlistEntry) -> Result<(), String> {
+        self.insert(&self.config.allowlist, allowlist_document(entry))
+            .await
+    }
+
+    async fn remove_allowlist_entry(&self, id: &str) -> Result<bool, String> {
+        let result = self
+            .collection(&self.config.allowlist)
+            .delete_one(doc! { "entry_id": id }, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn plaintext_secrets(&self) -> Result<Vec<(String, String)>, String> {
+        let documents = self.find(&self.config.plaintext_secrets, doc! {}).await?;
+        Ok(documents
+            .iter()
+            .filter_map(|x| {
+                let id = x.get_object_id("_id").ok()?.to_hex();
+                let secret = x.get_str("secret").ok()?.to_string();
+                Some((id, secret))
+            })
+            .collect())
+    }
+
+    async fn remove_plaintext_secret(&self, id: &str) -> Result<(), String> {
+        let id = ObjectId::parse_str(id).map_err(|e| e.to_string())?;
+        self.collection(&self.config.plaintext_secrets)
+            .delete_one(doc! { "_id": id }, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn insert_job(&self, job: &JobRecord) -> Result<(), String> {
+        let results: Vec<Bson> = job
+            .results
+            .iter()
+            .map(|x| to_bson(x).unwrap_or(Bson::Null))
+            .collect();
+        self.insert(
+            &self.config.jobs,
+            doc! {
+                "job_id": &job.job_id,
+                "message": &job.message,
+                "status": job.status,
+                "result": results,
+                "created_at": &job.created_at,
+            },
+        )
+        .await
+    }
+
+    async fn triage_records(&self, hashes: Option<&[String]>) -> Result<Vec<TriageRecord>, String> {
+        let filter = match hashes {
+            Some(hashes) => doc! { "hash": { "$in": hashes } },
+            None => doc! {},
+        };
+        let documents = self.find(&self.config.triage, filter).await?;
+        Ok(documents
+            .iter()
+            .map(|x| {
+                let get = |key: &str| x.get_str(key).unwrap_or("").to_string();
+                TriageRecord {
+                    hash: get("hash"),
+                    state: get("state"),
+                    justification: get("justification"),
+                    owner: get("owner"),
+                    expires_at: get("expires_at"),
+                    updated_at: get("updated_at"),
+                }
+            })
+            .collect())
+    }
+
+    async fn set_triage_record(&self, record: &TriageRecord) -> Result<(), String> {
+        let document = doc! {
+            "hash": &record.hash,
+            "state": &record.state,
+            "justification": &record.justification,
+            "owner": &record.owner,
+            "expires_at": &record.expires_at,
+            "updated_at": &record.updated_at,
+        };
+        self.collection(&self.config.triage)
+            .replace_one(
+                doc! { "hash": &record.hash },
+                document,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection};
+
+use super::{FindingRecord, FindingStore, JobRecord, StoreConfig, TriageRecord};
+use crate::secrets::allowlist::AllowlistEntry;
+
+// sqlite limits the number of parameters of a statement
+const QUERY_CHUNK_SIZE: usize = 500;
+
+// Single file store for teams without a MongoDB, the collection names of the config
+// are used as table names
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    config: StoreConfig,
+}
+
+fn table(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(",")
+}
+
+impl SqliteStore {
+    pub fn open(path: &str, config: StoreConfig) -> Result<SqliteStore, String> {
+        let connection = Connection::open(path).map_err(|e| e.to_string())?;
+        connection
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {hashes} (
+                    hash TEXT PRIMARY KEY, repo TEXT NOT NULL, branch TEXT NOT NULL,
+                    job_id TEXT NOT NULL, created_at TEXT NOT NULL);
+                CREATE TABLE IF NOT EXISTS {allowlist} (
+                    entry_id TEXT PRIMARY KEY, salt TEXT NOT NULL, hash TEXT NOT NULL,
+                    detector TEXT NOT NULL, path TEXT NOT NULL, expires_at TEXT NOT NULL,
+                    reason TEXT NOT NULL, created_at TEXT NOT NULL);
+                CREATE TABLE IF NOT EXISTS {jobs} (
+                    job_id TEXT NOT NULL, message TEXT NOT NULL, status INTEGER NOT NULL,
+                    result TEXT NOT NULL, created_at TEXT NOT NULL);
+                CREATE TABLE IF NOT EXISTS {triage} (
+                    hash TEXT PRIMARY KEY, state TEXT NOT NULL, justification TEXT NOT NULL,
+                    owner TEXT NOT NULL, expires_at TEXT NOT NULL, updated_at TEXT NOT NULL);",
+                hashes = table(&config.hashes),
+                allowlist = table(&config.allowlist),
+                jobs = table(&config.jobs),
+                triage = table(&config.triage),
+            ))
+            .map_err(|e| e.to_string())?;
+        Ok(SqliteStore {
+            connection: Mutex::new(connection),
+            config,
+        })
+    }
+}
+
+#[async_trait]
+impl FindingStore for SqliteStore {
+    async fn known_hashes(&self, hashes: &[String]) -> Result<HashSet<String>, String> {
+        let connection = self.connection.lock().unwrap();
+        let mut known = HashSet::new();
+        for chunk in hashes.chunks(QUERY_CHUNK_SIZE) {
+            let mut statement = connection
+                .prepare(&format!(
+                    "SELECT hash FROM {} WHERE hash IN ({})",
+                    table(&self.config.hashes),
+                    placeholders(chunk.len())
+                ))
+                .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map(params_from_iter(chunk.iter()), |row| {
+                    row.get::<_, String>(0)
+                })
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                known.insert(row.map_err(|e| e.to_string())?);
+            }
+        }
+        Ok(known)
+    }
+
+    async fn register_finding(&self, record: &FindingRecord) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                &format!(
+                    "INSERT OR IGNORE INTO {} (hash, repo, branch, job_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    table(&self.config.hashes)
+                ),
+                params![
+                    record.hash,
+                    record.repo,
+                    record.branch,
+                    record.job_id,
+                    Utc::now().to_rfc3339()
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn allowlist_entries(&self) -> Result<Vec<AllowlistEntry>, String> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT entry_id, salt, hash, detector, path, expires_at, reason, created_at FROM {}",
+                table(&self.config.allowlist)
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| {
+                let expires_at: String = row.get(5)?;
+                Ok(AllowlistEntry {
+                    id: row.get(0)?,
+                    salt: row.get(1)?,
+                    hash: row.get(2)?,
+                    detector: row.get(3)?,
+                    path: row.get(4)?,
+                    expires_at: DateTime::parse_from_rfc3339(&expires_at)
+                        .ok()
+                        .map(|x| x.with_timezone(&Utc)),
+                    reason: row.get(6)?,
+                    created_at: row.get(7)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<AllowlistEntry>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    async fn add_allowlist_entry(&self, entry: &AllowlistEntry) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                &format!(
+                    "INSERT INTO {} (entry_id, salt, hash, detector, path, expires_at, reason, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    table(&self.config.allowlist)
+                ),
+                params![
+                    entry.id,
+                    entry.salt,
+                    entry.hash,
+                    entry.detector,
+                    entry.path,
+                    entry.expires_at.map(|x| x.to_rfc3339()).unwrap_or_default(),
+                    entry.reason,
+                    entry.created_at
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn remove_allowlist_entry(&self, id: &str) -> Result<bool, String> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE entry_id = ?1",
+                    table(&self.config.allowlist)
+                ),
+                params![id],
+            )
+            .map(|x| x > 0)
+            .map_err(|e| e.to_string())
+    }
+
+    // secrets were never stored in plaintext in SQLite
+    async fn plaintext_secrets(&self) -> Result<Vec<(String, String)>, String> {
+        Ok(Vec::new())
+    }
+
+    async fn remove_plaintext_secret(&self, _id: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn insert_job(&self, job: &JobRecord) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                &format!(
+                    "INSERT INTO {} (job_id, message, status, result, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    table(&self.config.jobs)
+                ),
+                params![
+                    job.job_id,
+                    job.message,
+                    job.status,
+                    serde_json::to_string(&job.results).unwrap(),
+                    job.created_at
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn triage_records(&self, hashes: Option<&[String]>) -> Result<Vec<TriageRecord>, String> {
+        let connection = self.connection.lock().unwrap();
+        let query = format!(
+            "SELECT hash, state, justification, owner, expires_at, updated_at FROM {}",
+            table(&self.config.triage)
+        );
+        let chunks: Vec<&[String]> = match hashes {
+            Some(hashes) => hashes.chunks(QUERY_CHUNK_SIZE).collect(),
+            None => vec![&[]],
+        };
+        let mut records = Vec::new();
+        for chunk in chunks {
+            let mut statement = match hashes {
+                Some(_) => connection.prepare(&format!(
+                    "{} WHERE hash IN ({})",
+                    query,
+                    placeholders(chunk.len())
+                )),
+                None => connection.prepare(&query),
+            }
+            .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map(params_from_iter(chunk.iter()), |row| {
+                    Ok(TriageRecord {
+                        hash: row.get(0)?,
+                        state: row.get(1)?,
+                        justification: row.get(2)?,
+                        owner: row.get(3)?,
+                        expires_at: row.get(4)?,
+                        updated_at: row.get(5)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                records.push(row.map_err(|e| e.to_string())?);
+            }
+        }
+        Ok(records)
+    }
+
+    async fn set_triage_record(&self, record: &TriageRecord) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (hash, state, justification, owner, expires_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    table(&self.config.triage)
+                ),
+                params![
+                    record.hash,
+                    record.state,
+                    record.justification,
+                    record.owner,
+                    record.expires_at,
+                    record.updated_at
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::fingerprint::relative_path;
+use crate::secrets::{finding_file, finding_line};
+
+pub const DEFAULT_BASELINE_FILE: &str = ".hela-baseline.json";
+const BASELINE_VERSION: u32 = 1;
+
+// A known finding, the baseline can be committed to the repository as it has no secret
+// values and their fingerprints don't hash them either
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    #[serde(rename = "type")]
+    pub scan_type: String,
+    pub rule: String,
+    pub path: String,
+    #[serde(default)]
+    pub line: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Baseline {
+    pub version: u32,
+    pub created_at: String,
+    pub updated_at: String,
+    pub findings: Vec<BaselineEntry>,
+    #[serde(skip)]
+    fingerprints: HashSet<String>,
+}
+
+impl Baseline {
+    fn new(findings: Vec<BaselineEntry>) -> Baseline {
+        let now = Utc::now().to_rfc3339();
+        let mut baseline = Baseline {
+            version: BASELINE_VERSION,
+            created_at: now.clone(),
+            updated_at: now,
+            findings,
+            fingerprints: HashSet::new(),
+        };
+        baseline.index();
+        baseline
+    }
+
+    fn index(&mut self) {
+        self.fingerprints = self
+            .findings
+            .iter()
+            .map(|x| x.fingerprint.clone())
+            .collect();
+    }
+
+    // Every finding of the scan output (/tmp/output.json) except suppressed ones, `root`
+    // is the scanned directory paths are made relative to
+    pub fn from_output(output: &Value, root: &str) -> Baseline {
+        let mut findings = Vec::new();
+        let mut add = |finding: &Value, scan_type: &str, rule: &str, path: &str, line: i64| {
+            let fingerprint = match finding["fingerprint"].as_str() {
+                Some(fingerprint) if finding["suppression"].is_null() => fingerprint,
+                _ => return,
+            };
+            findings.push(BaselineEntry {
+                fingerprint: fingerprint.to_string(),
+                scan_type: scan_type.to_string(),
+                rule: rule.to_string(),
+                path: relative_path(path, root),
+                line,
+            });
+        };
+        for result in output["sast"].as_array().unwrap_or(&vec![]) {
+            add(
+                result,
+                "sast",
+                result["check_id"].as_str().unwrap_or(""),
+                result["path"].as_str().unwrap_or(""),
+                result["start"]["line"].as_i64().unwrap_or(0),
+            );
+        }
+        if let Some(manifests) = output["sca"].as_object() {
+            for (manifest, result) in manifests {
+                for package in result["packages"].as_array().unwrap_or(&vec![]) {
+                    for vuln in package["vulnerabilities"].as_array().unwrap_or(&vec![]) {
+                        add(vuln, "sca", vuln["id"].as_str().unwrap_or(""), manifest, 0);
+                    }
+                }
+            }
+        }
+        for result in output["secret"]["results"].as_array().unwrap_or(&vec![]) {
+            add(
+                result,
+                "secret",
+                result["DetectorName"].as_str().unwrap_or(""),
+                finding_file(result),
+                finding_line(result),
+            );
+        }
+        findings.sort_by(|a, b| (&a.path, a.line, &a.rule).cmp(&(&b.path, b.line, &b.rule)));
+        let mut seen = HashSet::new();
+        findings.retain(|x| seen.insert(x.fingerprint.clone()));
+        Baseline::new(findings)
+    }
+
+    pub fn load(path: &str) -> Result<Baseline, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut baseline = serde_json::from_str::<Baseline>(&data)
+            .map_err(|e| format!("invalid baseline: {}", e))?;
+        baseline.index();
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap()).map_err(|e| e.to_string())
+    }
+
+    // Replaces the findings with the ones of `current` and keeps the creation date,
+    // returns the number of added and removed findings
+    pub fn update(&mut self, current: Baseline) -> (usize, usize) {
+        let added = current
+            .findings
+            .iter()
+            .filter(|x| !self.contains(&x.fingerprint))
+            .count();
+        let removed = self
+            .findings
+            .iter()
+            .filter(|x| !current.contains(&x.fingerprint))
+            .count();
+        self.version = BASELINE_VERSION;
+        self.updated_at = current.updated_at;
+        self.findings = current.findings;
+        self.index();
+        (added, removed)
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+
+    // whether a finding of the scan output is in the baseline
+    pub fn contains_finding(&self, finding: &Value) -> bool {
+        finding["fingerprint"]
+            .as_str()
+            .is_some_and(|x| self.contains(x))
+    }
+}
@@ -1,27 +1,21 @@
-use bson::to_bson;
-
-use futures::StreamExt;
-use mongodb::{
-    bson::{doc, Bson, Document},
-    error::Error,
-    options::ClientOptions,
-    Client, Collection,
-};
+use mongodb::{options::ClientOptions, Client};
 
-use chrono::Utc;
 use regex::Regex;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
+use std::env;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
 use std::{collections::HashMap, process::Command};
-use std::{collections::HashSet, env};
 use std::{
     fs::{self, File},
     path::PathBuf,
 };
 
+use super::git_auth::{clone_args, git_env};
+
 // define static exit codes and message
 pub const EXIT_CODE_LICENSE_FAILED: i32 = 101;
 pub const LICENSE_FAILED_MSG: &str = "License scan failed";
@@ -93,54 +87,6 @@ pub async fn slack_alert(url: &str, message: &str) {
     return;
 }
 
-pub async fn bulk_check_hash_exists(
-    hashes: &[String],
-    mongo_uri: &str,
-) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let client = connect_to_mongodb(mongo_uri, "code-security-open-source").await?;
-    let existing_hashes = find_messages_in_hashes(&client, hashes).await?;
-    Ok(existing_hashes)
-}
-
-pub async fn list_whitelisted_secrets(
-    mongo_uri: &str,
-) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let client = connect_to_mongodb(mongo_uri, "code-security-open-source").await?;
-    let collection: Collection<Document> = client
-        .database("code-security-open-source")
-        .collection("secrets");
-
-    // Create the filter to match the secret
-    let mut cursor = collection.find(None, None).await?;
-    let mut secrets_list: HashSet<String> = HashSet::new(); // Make this mutable
-    while let Some(doc) = cursor.next().await {
-        match doc {
-            Ok(document) => {
-                if let Some(secret) = document.get_str("secret").ok() {
-                    secrets_list.insert(secret.to_string());
-                }
-            }
-            Err(e) => return Err(e.into()),
-        }
-    }
-    Ok(secrets_list) // Return the secrets_list
-}
-
-pub async fn register_hash(message: &str, mongo_uri: &str) {
-    let hashed_message = hash_text(message);
-    match connect_to_mongodb(mongo_uri, "code-security-open-source").await {
-        Ok(client) => {
-            let collection = client
-                .database("code-security-open-source")
-                .collection("hashes");
-            let document = doc! { "message": hashed_message };
-            collection.insert_one(document, None).await.unwrap();
-        }
-        Err(e) => {
-            print_error(&format!("Error: {}", e.to_string()), 101);
-        }
-    }
-}
 pub fn print_error(error: &str, error_code: i32) {
     if error.to_lowercase().starts_with("warning") {
         println!("[❕] {}", error);
@@ -158,86 +104,48 @@ pub fn count_env_variables(input: &str) -> i128 {
     count as i128
 }
 
+// only the user info of a url is secret, urls without one are kept as they are
 pub fn redact_github_token(input: &str) -> String {
-    let exploded = input.split("@").collect::<Vec<&str>>();
-    let secret = exploded[0].split("/").last().unwrap();
-    let redacted_string = input.replace(secret, "********");
-    redacted_string
+    let pattern = Regex::new(r"^(https?://)[^/@]+@").unwrap();
+    pattern.replace(input, "${1}********@").to_string()
 }
 
-async fn connect_to_mongodb(
+// CI systems set at least one of these variables
+pub fn is_ci() -> bool {
+    [
+        "CI",
+        "GITHUB_ACTIONS",
+        "GITLAB_CI",
+        "JENKINS_URL",
+        "BUILDKITE",
+        "CIRCLECI",
+        "TF_BUILD",
+        "BITBUCKET_BUILD_NUMBER",
+    ]
+    .iter()
+    .any(|x| env::var(x).map(|x| !x.is_empty()).unwrap_or(false))
+}
+
+// one client per uri for the whole run, the driver keeps a connection pool per client
+static MONGO_CLIENTS: OnceLock<Mutex<HashMap<String, Client>>> = OnceLock::new();
+
+pub async fn connect_to_mongodb(
     mongo_uri: &str,
     _db_name: &str,
 ) -> Result<Client, mongodb::error::Error> {
+    let clients = MONGO_CLIENTS.get_or_init(Default::default);
+    if let Some(client) = clients.lock().unwrap().get(mongo_uri) {
+        return Ok(client.clone());
+    }
     let client_options = ClientOptions::parse(mongo_uri).await?;
     let client = Client::with_options(client_options)?;
+    clients
+        .lock()
+        .unwrap()
+        .insert(mongo_uri.to_string(), client.clone());
     Ok(client)
 }
 
-pub async fn find_messages_in_hashes(
-    client: &Client,
-    hashes: &[String],
-) -> Result<HashSet<String>, mongodb::error::Error> {
-    let collection: Collection<Document> = client
-        .database("code-security-open-source")
-        .collection("hashes");
-
-    // Create the filter to match any of the hashes
-    let filter = doc! { "hash": { "$in": hashes } };
-
-    // Query the database
-    let mut cursor = collection.find(filter, None).await?;
-    let mut existing_hashes = HashSet::new();
-
-    while let Some(doc) = cursor.next().await {
-        match doc {
-            Ok(document) => {
-                if let Some(hash) = document.get_str("hash").ok() {
-                    existing_hashes.insert(hash.to_string());
-                }
-            }
-            Err(e) => return Err(e),
-        }
-    }
-
-    Ok(existing_hashes)
-}
-
-pub async fn insert_job_info(
-    mongo_uri: &str,
-    job_id: &str,
-    msg: &str,
-    status: &i32,
-    results: Vec<Value>,
-) -> Result<(), Error> {
-    // Connect to MongoDB
-    let client = connect_to_mongodb(mongo_uri, "code-security-open-source").await?;
-
-    // Get the collection
-    let collection = client
-        .database("code-security-open-source")
-        .collection("jobs");
-
-    // Convert serde_json::Value to Bson
-    let bson_results: Vec<Bson> = results
-        .into_iter()
-        .map(|v| to_bson(&v).unwrap_or(Bson::Null))
-        .collect();
-    // Create the document to insert
-    let document = doc! {
-        "job_id": job_id,
-        "message": msg,
-        "status": status,
-        "result": bson_results,
-        "created_at": Utc::now().to_rfc3339(),
-    };
-
-    // Insert the document into the collection
-    collection.insert_one(document, None).await?;
-
-    Ok(())
-}
-
 pub async fn execute_command(command: &str, suppress_error: bool) -> String {
     let suppress_error = suppress_error || false;
     let exec_name = command.split_whitespace().next().unwrap();
@@ -459,7 +367,12 @@ pub fn checkout(
     // Step 1: Clone the repository
     set_git_global_user_config()?;
     let mut clone_cmd = Command::new("git");
-    clone_cmd.arg("clone").arg(clone_url).arg(clone_path);
+    clone_cmd
+        .arg("clone")
+        .args(clone_args(base_branch.is_some()))
+        .arg(clone_url)
+        .arg(clone_path)
+        .envs(git_env());
     if let Some(branch) = base_branch {
         clone_cmd.arg("--branch").arg(branch);
     }
@@ -477,6 +390,7 @@ pub fn checkout(
     if let Some(pr) = pr_branch {
         let fetch_output = Command::new("git")
             .args(&["fetch", "origin", pr])
+            .envs(git_env())
             .output()?;
         if !fetch_output.status.success() {
             let error_msg = String::from_utf8_lossy(&fetch_output.stderr);
@@ -507,6 +421,180 @@ pub fn checkout(
     Ok(())
 }
 
+// Runs git in `dir`, returns its trimmed output
+pub fn git(dir: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .envs(git_env())
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("unable to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Commit of a branch, tag or commit id, refs which only exist on the remote are fetched
+pub fn resolve_ref(dir: &str, name: &str) -> Result<String, String> {
+    for candidate in [name.to_string(), format!("origin/{}", name)] {
+        if let Ok(commit) = git(
+            dir,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", candidate),
+            ],
+        ) {
+            return Ok(commit);
+        }
+    }
+    git(dir, &["fetch", "--quiet", "origin", name])
+        .map_err(|e| format!("unknown ref {}: {}", name, e))?;
+    git(dir, &["rev-parse", "--verify", "FETCH_HEAD^{commit}"])
+}
+
+// Fills `clone_path` with the project at `reference` (a branch, tag or commit id). A remote
+// project is cloned and checked out, the tracked files of a local repository are exported
+// with git archive so ignored files, build outputs and .git stay out and the repository
+// isn't touched. Returns the commit.
+pub fn checkout_ref(path: &str, clone_path: &str, reference: &str) -> Result<String, String> {
+    if path.starts_with("http") {
+        let parent = Path::new(clone_path)
+            .parent()
+            .and_then(|x| x.to_str())
+            .unwrap_or("/");
+        let mut args = vec!["clone".to_string(), "--quiet".to_string()];
+        args.extend(clone_args(false));
+        args.extend([path.to_string(), clone_path.to_string()]);
+        git(
+            parent,
+            &args.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
+        )?;
+        let commit = resolve_ref(clone_path, reference)?;
+        git(
+            clone_path,
+            &["checkout", "--quiet", "--force", "--detach", &commit],
+        )?;
+        return Ok(commit);
+    }
+    let commit = resolve_ref(path, reference)?;
+    fs::create_dir_all(clone_path).map_err(|e| e.to_string())?;
+    let mut archive = Command::new("git")
+        .args(["archive", "--format=tar", &commit])
+        .current_dir(path)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("unable to run git: {}", e))?;
+    let extract = Command::new("tar")
+        .args(["-x", "-C", clone_path])
+        .stdin(archive.stdout.take().unwrap())
+        .output()
+        .map_err(|e| format!("unable to run tar: {}", e))?;
+    let archived = archive.wait().map_err(|e| e.to_string())?;
+    if !archived.success() || !extract.status.success() {
+        return Err(format!(
+            "unable to export {}: {}",
+            reference,
+            String::from_utf8_lossy(&extract.stderr).trim()
+        ));
+    }
+    Ok(commit)
+}
+
+// Fills `clone_path` with the working tree of a local repository: tracked files as they
+// are on disk and untracked ones which aren't ignored, so build outputs and dependencies
+// stay out like with git archive. `with_git` copies .git as well for history scans.
+pub fn export_working_tree(path: &str, clone_path: &str, with_git: bool) -> Result<(), String> {
+    let files = git(
+        path,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+    )?;
+    for file in files.split('\0').filter(|x| !x.is_empty()) {
+        let source = Path::new(path).join(file);
+        // tracked files deleted from the working tree are listed as well
+        if !source.is_file() {
+            continue;
+        }
+        let target = Path::new(clone_path).join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::copy(&source, &target).map_err(|e| format!("unable to copy {}: {}", file, e))?;
+    }
+    fs::create_dir_all(clone_path).map_err(|e| e.to_string())?;
+    if with_git {
+        let output = Command::new("cp")
+            .args([
+                "-r",
+                &format!("{}/.git", path),
+                &format!("{}/.git", clone_path),
+            ])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!(
+                "unable to copy {}/.git: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Fills `clone_path` up front with the project at `reference`, or with the working tree of
+// a local git repository if there is no reference. Returns false if the scans still have
+// to clone or copy the project themselves.
+pub fn prepare_checkout(
+    path: &str,
+    clone_path: &str,
+    reference: &str,
+    with_git: bool,
+) -> Result<bool, String> {
+    let local = !path.starts_with("http");
+    if !reference.is_empty() {
+        let _ = fs::remove_dir_all(clone_path);
+        let commit = checkout_ref(path, clone_path, reference)
+            .map_err(|e| format!("unable to check out {}: {}", reference, e))?;
+        println!("[+] Scanning {} ({}) of {}", reference, &commit[..12], path);
+        return Ok(true);
+    }
+    // a project which isn't a git repository is copied by the scans as it is
+    if local && Path::new(&format!("{}/.git", path)).exists() {
+        let _ = fs::remove_dir_all(clone_path);
+        export_working_tree(path, clone_path, with_git)
+            .map_err(|e| format!("unable to copy {}: {}", path, e))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// Branch checked out in `repo_dir`, None for a detached HEAD or no git repository
+pub fn current_branch(repo_dir: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(repo_dir)
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || branch.is_empty() || branch == "HEAD" {
+        return None;
+    }
+    Some(branch)
+}
+
 pub fn find_commit_for_snippet(
     file_path: &str,
     code_snippet: &str,
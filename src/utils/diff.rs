@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{
+    common::{export_working_tree, git, resolve_ref},
+    fingerprint::relative_path,
+    git_auth::clone_args,
+};
+use crate::secrets::{finding_file, finding_line};
+
+// Lines of the head version of every file changed between two refs, findings outside
+// of them are left out of a diff scan
+#[derive(Debug, Default)]
+pub struct DiffScope {
+    files: HashMap<String, Vec<(usize, usize)>>,
+    context: usize,
+}
+
+impl DiffScope {
+    // Checks out `head` (the working tree of a local project if empty) with the full tree
+    // at `clone_path` and collects the changes since its merge base with `base`. Nothing
+    // in the checkout is deleted, so cross-file analysis and installs keep working.
+    pub fn prepare(
+        path: &str,
+        clone_path: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<DiffScope, String> {
+        let parent = std::path::Path::new(clone_path)
+            .parent()
+            .and_then(|x| x.to_str())
+            .unwrap_or("/");
+        if path.starts_with("http") {
+            let mut args = vec!["clone".to_string(), "--quiet".to_string()];
+            // the merge base has to be in the clone
+            args.extend(clone_args(true));
+            args.extend([path.to_string(), clone_path.to_string()]);
+            git(
+                parent,
+                &args.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
+            )?;
+        } else {
+            export_working_tree(path, clone_path, true)?;
+        }
+        let base_commit = resolve_ref(clone_path, base)?;
+        let head_commit = if head.is_empty() {
+            git(clone_path, &["rev-parse", "HEAD"])?
+        } else {
+            let commit = resolve_ref(clone_path, head)?;
+            git(
+                clone_path,
+                &["checkout", "--quiet", "--force", "--detach", &commit],
+            )?;
+            commit
+        };
+        // without --head a local project is compared as it is, including uncommitted changes
+        if head.is_empty() && !path.starts_with("http") {
+            DiffScope::from_refs(clone_path, &base_commit, "")
+        } else {
+            DiffScope::from_refs(clone_path, &base_commit, &head_commit)
+        }
+    }
+
+    // Changes of `head` (the working tree if empty) since its merge base with `base` in
+    // the repository at `dir`, which is a copy as the working tree's index is changed
+    pub fn from_refs(dir: &str, base: &str, head: &str) -> Result<DiffScope, String> {
+        if head.is_empty() {
+            // untracked files which aren't ignored are new files of the working tree
+            git(dir, &["add", "--intent-to-add", "."])?;
+        }
+        let head_commit = if head.is_empty() { "HEAD" } else { head };
+        let merge_base = git(dir, &["merge-base", base, head_commit])?;
+        let mut args = vec![
+            "-c",
+            "core.quotepath=off",
+            "diff",
+            "--unified=0",
+            "--no-color",
+            "--no-ext-diff",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            merge_base.as_str(),
+        ];
+        if !head.is_empty() {
+            args.push(head);
+        }
+        Ok(DiffScope::parse(&git(dir, &args)?))
+    }
+
+    // SAST findings within `context` lines of a change are kept as well
+    pub fn with_context(mut self, context: usize) -> DiffScope {
+        self.context = context;
+        self
+    }
+
+    fn parse(diff: &str) -> DiffScope {
+        let hunk = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)(?:,(\d+))? @@").unwrap();
+        let mut scope = DiffScope::default();
+        let mut current: Option<String> = None;
+        for line in diff.lines() {
+            if let Some(file) = line.strip_prefix("+++ ") {
+                current = file.strip_prefix("b/").map(|x| x.to_string());
+                if let Some(file) = &current {
+                    scope.files.entry(file.clone()).or_default();
+                }
+                continue;
+            }
+            let (file, captures) = match (&current, hunk.captures(line)) {
+                (Some(file), Some(captures)) => (file, captures),
+                _ => continue,
+            };
+            let start: usize = captures[1].parse().unwrap_or(0);
+            let count: usize = captures
+                .get(2)
+                .map(|x| x.as_str().parse().unwrap_or(0))
+                .unwrap_or(1);
+            // a pure deletion has no lines in the head version
+            if count > 0 {
+                scope
+                    .files
+                    .entry(file.clone())
+                    .or_default()
+                    .push((start, start + count - 1));
+            }
+        }
+        scope
+    }
+
+    pub fn changed_files(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn contains_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    // whether lines `start` to `end` of `path` are within `context` lines of a change
+    fn contains_lines(&self, path: &str, start: usize, end: usize, context: usize) -> bool {
+        let end = end.max(start);
+        self.files.get(path).is_some_and(|x| {
+            x.iter()
+                .any(|(from, to)| start <= to + context && end + context >= *from)
+        })
+    }
+
+    pub fn filter_sast(&self, output: &mut Value, root: &str) -> usize {
+        let results = match output["sast"].as_array_mut() {
+            Some(results) => results,
+            None => return 0,
+        };
+        let before = results.len();
+        results.retain(|x| {
+            self.contains_lines(
+                &relative_path(x["path"].as_str().unwrap_or(""), root),
+                x["start"]["line"].as_u64().unwrap_or(0) as usize,
+                x["end"]["line"].as_u64().unwrap_or(0) as usize,
+                self.context,
+            )
+        });
+        before - results.len()
+    }
+
+    // Removes findings outside the changes from a scan output (/tmp/output.json), `root`
+    // is the scanned directory. SAST and secret findings have to overlap changed lines,
+    // SCA and license results are kept for changed manifests. Returns the number of
+    // removed findings.
+    pub fn filter_output(&self, output: &mut Value, root: &str) -> usize {
+        let mut removed = self.filter_sast(output, root);
+        if let Some(results) = output["secret"]["results"].as_array_mut() {
+            let before = results.len();
+            results.retain(|x| {
+                let path = relative_path(finding_file(x), root);
+                // the line of a history finding is the one of its commit
+                if x["SourceMetadata"]["Data"]["Git"].is_null() {
+                    let line = finding_line(x) as usize;
+                    self.contains_lines(&path, line, line, 0)
+                } else {
+                    self.contains_file(&path)
+                }
+            });
+            removed += before - results.len();
+        }
+        for key in ["sca", "license"] {
+            if let Some(manifests) = output[key].as_object_mut() {
+                let unchanged: Vec<String> = manifests
+                    .keys()
+                    .filter(|x| !self.contains_file(&relative_path(x, root)))
+                    .cloned()
+                    .collect();
+                for manifest in unchanged {
+                    if let Some(result) = manifests.remove(&manifest) {
+                        if key == "sca" {
+                            removed += result["packages"]
+                                .as_array()
+                                .into_iter()
+                                .flatten()
+                                .map(|x| x["vulnerabilities"].as_array().map_or(0, |x| x.len()))
+                                .sum::<usize>();
+                        }
+                    }
+                }
+            }
+        }
+        removed
+    }
+}
+
+// Applies a filter to the scan output file and returns the number of removed findings
+pub fn filter_output_file(path: &str, filter: impl Fn(&mut Value) -> usize) -> usize {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(_) => return 0,
+    };
+    let mut output = serde_json::from_str::<Value>(&data).unwrap_or_default();
+    let removed = filter(&mut output);
+    if removed > 0 {
+        let _ = std::fs::write(path, serde_json::to_string_pretty(&output).unwrap());
+    }
+    removed
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::common::hash_text;
+use crate::secrets::{finding_file, finding_line};
+
+// Path relative to the scanned project, fingerprints and ignore rules must not depend
+// on where the project was checked out
+pub fn relative_path(path: &str, root: &str) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .replace("//", "/")
+}
+
+fn fingerprint(parts: &[&str]) -> String {
+    hash_text(&parts.join("\n"))[..16].to_string()
+}
+
+// whitespace is collapsed so reindenting code doesn't change the fingerprint
+fn normalize_snippet(snippet: &str) -> String {
+    snippet.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// non-blank lines before and after a finding which go into its context hash
+const CONTEXT_LINES: usize = 2;
+
+// Lines `start` to `end` (1-based) of a file, empty if they aren't in `source`
+fn snippet(source: &[String], start: usize, end: usize) -> String {
+    if start == 0 || start > source.len() {
+        return String::new();
+    }
+    source[start - 1..end.clamp(start, source.len())].join("\n")
+}
+
+// Hash of the finding lines and the non-blank lines around them, tells findings with
+// the same snippet apart but changes when the surrounding code does
+pub fn context_hash(source: &[String], start: usize, end: usize) -> Option<String> {
+    let snippet = snippet(source, start, end);
+    if snippet.is_empty() {
+        return None;
+    }
+    let end = end.clamp(start, source.len());
+    let non_blank = |x: &&String| !x.trim().is_empty();
+    let mut before: Vec<&String> = source[..start - 1]
+        .iter()
+        .rev()
+        .filter(non_blank)
+        .take(CONTEXT_LINES)
+        .collect();
+    before.reverse();
+    let after: Vec<&String> = source[end..]
+        .iter()
+        .filter(non_blank)
+        .take(CONTEXT_LINES)
+        .collect();
+    let context: Vec<String> = before
+        .into_iter()
+        .chain(after)
+        .map(|x| normalize_snippet(x))
+        .collect();
+    Some(fingerprint(&[
+        &normalize_snippet(&snippet),
+        &context.join("\n"),
+    ]))
+}
+
+// `source` are the lines of the scanned file, semgrep only reports the matched code
+// when logged in so it's taken from the file if possible
+pub fn sast_fingerprint(result: &Value, root: &str, source: &[String]) -> String {
+    let start = result["start"]["line"].as_u64().unwrap_or(0) as usize;
+    let end = result["end"]["line"].as_u64().unwrap_or(0) as usize;
+    let mut code = snippet(source, start, end);
+    if code.is_empty() {
+        code = result["extra"]["lines"].as_str().unwrap_or("").to_string();
+    }
+    fingerprint(&[
+        "sast",
+        result["check_id"].as_str().unwrap_or(""),
+        &relative_path(result["path"].as_str().unwrap_or(""), root),
+        &normalize_snippet(&code),
+    ])
+}
+
+// The secret itself isn't part of the fingerprint, a hash of it would end up in reports
+// and baselines and short secrets could be brute-forced from it. `source` are the lines of
+// the file the secret was found in, the line number is only used if it can't be read.
+pub fn secret_fingerprint(result: &Value, root: &str, source: &[String]) -> String {
+    let line = finding_line(result) as usize;
+    let location = context_hash(source, line, line).unwrap_or(line.to_string());
+    fingerprint(&[
+        "secret",
+        &result["DetectorName"].as_str().unwrap_or("").to_lowercase(),
+        &relative_path(finding_file(result), root),
+        &location,
+    ])
+}
+
+// every fingerprint of a scan output (/tmp/output.json)
+pub fn output_fingerprints(output: &Value) -> Vec<String> {
+    let mut findings: Vec<&Value> = Vec::new();
+    findings.extend(output["sast"].as_array().into_iter().flatten());
+    findings.extend(output["secret"]["results"].as_array().into_iter().flatten());
+    for result in output["sca"]
+        .as_object()
+        .into_iter()
+        .flat_map(|x| x.values())
+    {
+        for package in result["packages"].as_array().into_iter().flatten() {
+            findings.extend(package["vulnerabilities"].as_array().into_iter().flatten());
+        }
+    }
+    findings
+        .iter()
+        .filter_map(|x| x["fingerprint"].as_str())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+pub fn sca_fingerprint(manifest: &str, package: &Value, vuln: &Value, root: &str) -> String {
+    fingerprint(&[
+        "sca",
+        vuln["id"].as_str().unwrap_or(""),
+        &relative_path(manifest, root),
+        &format!(
+            "{}@{}",
+            package["package"]["name"].as_str().unwrap_or(""),
+            package["package"]["version"].as_str().unwrap_or("")
+        ),
+    ])
+}
+
+// Findings with the same fingerprint in one scan (e.g. the same call twice in a file)
+// get the number of the occurrence appended, the first one keeps its fingerprint
+#[derive(Default)]
+pub struct Occurrences {
+    seen: HashMap<String, usize>,
+}
+
+impl Occurrences {
+    pub fn disambiguate(&mut self, fingerprint: String) -> String {
+        let count = self.seen.entry(fingerprint.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            return fingerprint;
+        }
+        self::fingerprint(&[&fingerprint, &count.to_string()])
+    }
+}
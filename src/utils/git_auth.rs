@@ -0,0 +1,443 @@
+use std::{
+    env,
+    io::Write,
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use argparse::{ArgumentParser, Store};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use regex::Regex;
+use serde_json::{json, Value};
+
+pub const PROVIDERS: [&str; 5] = ["auto", "token", "ssh", "helper", "github-app"];
+
+#[derive(Clone, Debug)]
+pub struct AuthOptions {
+    // auto, token, ssh, helper or github-app
+    pub provider: String,
+    // variable holding the token, HELA_GIT_TOKEN and the usual CI variables if empty
+    pub token_env: String,
+    // private key for the ssh provider, the ssh agent and default keys if empty
+    pub ssh_key: String,
+    // credential helper for the helper provider, the configured ones if empty
+    pub credential_helper: String,
+    pub app_id: String,
+    // PEM private key of the GitHub App
+    pub app_key: String,
+    // looked up from the repository if empty
+    pub app_installation: String,
+    // 0 clones the whole history
+    pub depth: usize,
+    // partial clone filter, e.g. blob:none
+    pub filter: String,
+}
+
+impl Default for AuthOptions {
+    fn default() -> Self {
+        AuthOptions {
+            provider: "auto".to_string(),
+            token_env: String::new(),
+            ssh_key: String::new(),
+            credential_helper: String::new(),
+            app_id: String::new(),
+            app_key: String::new(),
+            app_installation: String::new(),
+            depth: 0,
+            filter: String::new(),
+        }
+    }
+}
+
+// What git commands talking to the remote get, set once before the scans. Credentials go
+// through GIT_CONFIG_* variables of the git processes only, so they never end up in the
+// clone url, .git/config, logs or the environment of the other tools.
+#[derive(Debug, Default)]
+struct GitAuth {
+    config: Vec<(String, String)>,
+    env: Vec<(String, String)>,
+    token: Option<String>,
+    depth: usize,
+    filter: String,
+}
+
+static GIT_AUTH: OnceLock<GitAuth> = OnceLock::new();
+
+fn git_auth() -> &'static GitAuth {
+    GIT_AUTH.get_or_init(GitAuth::default)
+}
+
+// Registers the authentication and clone options on the parser of every command which may
+// clone a project
+pub fn add_options<'a>(ap: &mut ArgumentParser<'a>, options: &'a mut AuthOptions) {
+    let AuthOptions {
+        provider,
+        token_env,
+        ssh_key,
+        credential_helper,
+        app_id,
+        app_key,
+        app_installation,
+        depth,
+        filter,
+    } = options;
+    ap.refer(provider).add_option(
+        &["--git-auth"],
+        Store,
+        "How to authenticate clones of a private repository: auto, token, ssh, helper or github-app (default: auto)",
+    );
+    ap.refer(token_env).add_option(
+        &["--git-token-env"],
+        Store,
+        "Environment variable with the access token, sent to any host (default: HELA_GIT_TOKEN, GITHUB_TOKEN, GITLAB_TOKEN, BITBUCKET_TOKEN for their hosts only)",
+    );
+    ap.refer(ssh_key).add_option(
+        &["--ssh-key"],
+        Store,
+        "Private key for --git-auth ssh (default: the ssh agent and default keys)",
+    );
+    ap.refer(credential_helper).add_option(
+        &["--git-credential-helper"],
+        Store,
+        "Credential helper for --git-auth helper (default: the helpers of the git config)",
+    );
+    ap.refer(app_id).add_option(
+        &["--github-app-id"],
+        Store,
+        "GitHub App id for --git-auth github-app",
+    );
+    ap.refer(app_key).add_option(
+        &["--github-app-key"],
+        Store,
+        "Path of the GitHub App private key (PEM) for --git-auth github-app",
+    );
+    ap.refer(app_installation).add_option(
+        &["--github-app-installation"],
+        Store,
+        "Installation id of the GitHub App (default: the installation on the repository)",
+    );
+    ap.refer(depth).add_option(
+        &["--clone-depth"],
+        Store,
+        "Shallow clone with this many commits, PR and --base clones keep the history they need (default: full history)",
+    );
+    ap.refer(filter).add_option(
+        &["--clone-filter"],
+        Store,
+        "Partial clone filter, e.g. blob:none",
+    );
+}
+
+// Url without the user info and the user and token taken from it
+fn split_credentials(url: &str) -> (String, Option<(String, String)>) {
+    let pattern = Regex::new(r"^(https?://)([^/@]+)@(.*)$").unwrap();
+    let captures = match pattern.captures(url) {
+        Some(captures) => captures,
+        None => return (url.to_string(), None),
+    };
+    let clean = format!("{}{}", &captures[1], &captures[3]);
+    let credentials = match captures[2].split_once(':') {
+        Some((user, token)) => (user.to_string(), token.to_string()),
+        // a token on its own, e.g. https://<token>@github.com/org/repo
+        None => (token_user(url).to_string(), captures[2].to_string()),
+    };
+    (clean, Some(credentials))
+}
+
+// scheme and host of a url with a trailing slash, the key of the url specific git config
+fn url_prefix(url: &str) -> String {
+    let pattern = Regex::new(r"^(https?://[^/]+)").unwrap();
+    pattern
+        .captures(url)
+        .map(|x| format!("{}/", &x[1]))
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|x| !x.is_empty())
+}
+
+fn url_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|x| x.to_lowercase())
+}
+
+// Hosting service of a url, known by its exact host (or the one of the CI it runs in) so
+// tokens of the environment never go to other hosts which merely look alike
+fn forge(url: &str) -> Option<Forge> {
+    let host = url_host(url)?;
+    let github_server = env_var("GITHUB_SERVER_URL").and_then(|x| url_host(&x));
+    let gitlab_server = env_var("CI_SERVER_HOST").map(|x| x.to_lowercase());
+    if host == "github.com" || github_server.as_ref() == Some(&host) {
+        Some(Forge::GitHub)
+    } else if host == "gitlab.com" || gitlab_server.as_ref() == Some(&host) {
+        Some(Forge::GitLab)
+    } else if host == "bitbucket.org" {
+        Some(Forge::Bitbucket)
+    } else {
+        None
+    }
+}
+
+// user name the hosting service expects next to an access token
+fn token_user(url: &str) -> &'static str {
+    match forge(url) {
+        Some(Forge::GitLab) => "oauth2",
+        Some(Forge::Bitbucket) => "x-token-auth",
+        _ => "x-access-token",
+    }
+}
+
+// Token of the environment, `token_env` for any host, otherwise HELA_GIT_TOKEN and the
+// variables of the host's CI if it's a known hosting service
+fn env_token(url: &str, token_env: &str) -> Option<(String, String)> {
+    let user = token_user(url).to_string();
+    if !token_env.is_empty() {
+        return env_var(token_env).map(|x| (user, x));
+    }
+    let names: &[&str] = match forge(url)? {
+        Forge::GitHub => &["HELA_GIT_TOKEN", "GITHUB_TOKEN", "GH_TOKEN"],
+        Forge::GitLab => &["HELA_GIT_TOKEN", "GITLAB_TOKEN"],
+        Forge::Bitbucket => &["HELA_GIT_TOKEN", "BITBUCKET_TOKEN"],
+    };
+    if let Some(token) = names.iter().find_map(|x| env_var(x)) {
+        return Some((user, token));
+    }
+    // the job token of GitLab CI has its own user
+    if forge(url) == Some(Forge::GitLab) {
+        return env_var("CI_JOB_TOKEN").map(|x| ("gitlab-ci-token".to_string(), x));
+    }
+    None
+}
+
+// Installation access token of a GitHub App, the app authenticates with a JWT signed by
+// openssl
+async fn github_app_token(url: &str, options: &AuthOptions) -> Result<String, String> {
+    if options.app_id.is_empty() || options.app_key.is_empty() {
+        return Err("the github-app provider needs --github-app-id and --github-app-key".into());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let unsigned = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+        URL_SAFE_NO_PAD
+            .encode(json!({"iat": now - 60, "exp": now + 540, "iss": options.app_id}).to_string())
+    );
+    let mut openssl = Command::new("openssl")
+        .args(["dgst", "-sha256", "-sign", &options.app_key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("unable to run openssl: {}", e))?;
+    openssl
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(unsigned.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let signature = openssl.wait_with_output().map_err(|e| e.to_string())?;
+    if !signature.status.success() {
+        return Err(format!(
+            "unable to sign the GitHub App JWT: {}",
+            String::from_utf8_lossy(&signature.stderr).trim()
+        ));
+    }
+    let jwt = format!("{}.{}", unsigned, URL_SAFE_NO_PAD.encode(signature.stdout));
+
+    let api = env_var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_string());
+    let api = api.trim_end_matches('/');
+    let client = reqwest::Client::new();
+    let request = |method: reqwest::Method, endpoint: String| {
+        client
+            .request(method, format!("{}{}", api, endpoint))
+            .header("Authorization", format!("Bearer {}", jwt))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "hela")
+    };
+    let installation = if options.app_installation.is_empty() {
+        let repo = url
+            .trim_start_matches(&url_prefix(url))
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .to_string();
+        let response = request(
+            reqwest::Method::GET,
+            format!("/repos/{}/installation", repo),
+        )
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "the GitHub App isn't installed on {} ({})",
+                repo,
+                response.status()
+            ));
+        }
+        let data: Value = response.json().await.map_err(|e| e.to_string())?;
+        data["id"].to_string()
+    } else {
+        options.app_installation.clone()
+    };
+    let response = request(
+        reqwest::Method::POST,
+        format!("/app/installations/{}/access_tokens", installation),
+    )
+    .send()
+    .await
+    .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "unable to create an installation token ({})",
+            response.status()
+        ));
+    }
+    let data: Value = response.json().await.map_err(|e| e.to_string())?;
+    data["token"]
+        .as_str()
+        .map(|x| x.to_string())
+        .ok_or("no token in the GitHub response".to_string())
+}
+
+// Resolves the credentials of the `provider` for the remote project at `url` and applies
+// them to all later git commands. Returns the url without credentials, which is the one to
+// clone, log and report.
+pub async fn setup(url: &str, options: &AuthOptions) -> Result<String, String> {
+    if !PROVIDERS.contains(&options.provider.as_str()) {
+        return Err(format!(
+            "unknown git auth provider {}, use {}",
+            options.provider,
+            PROVIDERS.join(", ")
+        ));
+    }
+    let (clean_url, url_credentials) = split_credentials(url);
+    let mut auth = GitAuth {
+        // a missing credential fails instead of waiting for a prompt
+        env: vec![("GIT_TERMINAL_PROMPT".to_string(), "0".to_string())],
+        depth: options.depth,
+        filter: options.filter.clone(),
+        ..Default::default()
+    };
+    let prefix = url_prefix(&clean_url);
+    if !prefix.is_empty() {
+        let credentials = match options.provider.as_str() {
+            "auto" => url_credentials.or_else(|| env_token(&clean_url, &options.token_env)),
+            "token" => {
+                match url_credentials.or_else(|| env_token(&clean_url, &options.token_env)) {
+                    Some(credentials) => Some(credentials),
+                    None if !options.token_env.is_empty() => {
+                        return Err(format!("no token found, set {}", options.token_env))
+                    }
+                    None if forge(&clean_url).is_none() => {
+                        return Err(format!(
+                            "no token for {}, pass the variable holding it with --git-token-env",
+                            url_host(&clean_url).unwrap_or_default()
+                        ))
+                    }
+                    None => return Err("no token found, set HELA_GIT_TOKEN".to_string()),
+                }
+            }
+            "github-app" => Some((
+                "x-access-token".to_string(),
+                github_app_token(&clean_url, options).await?,
+            )),
+            "ssh" => {
+                // the https url is kept for reporting, git fetches it over ssh
+                let host = prefix
+                    .trim_start_matches("https://")
+                    .trim_start_matches("http://")
+                    .trim_end_matches('/');
+                // the port of the https url isn't the one of ssh
+                let host = host.split(':').next().unwrap_or(host);
+                auth.config
+                    .push((format!("url.ssh://git@{}/.insteadOf", host), prefix.clone()));
+                if !options.ssh_key.is_empty() {
+                    // git runs the command through a shell, keep the path as one argument
+                    auth.env.push((
+                        "GIT_SSH_COMMAND".to_string(),
+                        format!(
+                            "ssh -i '{}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new",
+                            options.ssh_key.replace('\'', "'\\''")
+                        ),
+                    ));
+                }
+                None
+            }
+            _ => {
+                if !options.credential_helper.is_empty() {
+                    // the empty value drops the helpers of the git config
+                    auth.config
+                        .push(("credential.helper".to_string(), String::new()));
+                    auth.config.push((
+                        "credential.helper".to_string(),
+                        options.credential_helper.clone(),
+                    ));
+                }
+                None
+            }
+        };
+        if let Some((user, token)) = credentials {
+            auth.config.push((
+                format!("http.{}.extraHeader", prefix),
+                format!(
+                    "Authorization: Basic {}",
+                    STANDARD.encode(format!("{}:{}", user, token))
+                ),
+            ));
+            auth.token = Some(token);
+        }
+    }
+    GIT_AUTH
+        .set(auth)
+        .map_err(|_| "git authentication is already set up".to_string())?;
+    Ok(clean_url)
+}
+
+// Environment of git commands which may talk to the remote
+pub fn git_env() -> Vec<(String, String)> {
+    let auth = git_auth();
+    let mut env = auth.env.clone();
+    if !auth.config.is_empty() {
+        env.push((
+            "GIT_CONFIG_COUNT".to_string(),
+            auth.config.len().to_string(),
+        ));
+        for (i, (key, value)) in auth.config.iter().enumerate() {
+            env.push((format!("GIT_CONFIG_KEY_{}", i), key.clone()));
+            env.push((format!("GIT_CONFIG_VALUE_{}", i), value.clone()));
+        }
+    }
+    env
+}
+
+// Shallow and partial clone arguments, `full_history` for clones which need merge bases
+pub fn clone_args(full_history: bool) -> Vec<String> {
+    let auth = git_auth();
+    let mut args = Vec::new();
+    if auth.depth > 0 && !full_history {
+        args.push("--depth".to_string());
+        args.push(auth.depth.to_string());
+    }
+    if !auth.filter.is_empty() {
+        args.push(format!("--filter={}", auth.filter));
+    }
+    args
+}
+
+// token of the token and github-app providers, for API calls to the hosting service
+pub fn token() -> Option<String> {
+    git_auth().token.clone()
+}
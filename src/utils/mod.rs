@@ -1,3 +1,8 @@
+pub mod baseline;
 pub mod file_utils;
 pub mod common;
-pub mod pipeline;
\ No newline at end of file
+pub mod fingerprint;
+pub mod pipeline;
+pub mod triage;
+pub mod diff;
+pub mod git_auth;
\ No newline at end of file
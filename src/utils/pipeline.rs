@@ -1,14 +1,201 @@
 use core::time;
 use prettytable::{row, Table};
 use serde_json::{json, Value};
-use std::{collections::HashMap, process::exit};
+use std::{
+    collections::{HashMap, HashSet},
+    process::exit,
+    sync::Arc,
+};
+
+use chrono::Utc;
 
-use crate::utils::common::{
-    bulk_check_hash_exists, find_commit_for_snippet, insert_job_info, slack_alert,
-    upload_to_defect_dojo,
+use crate::license::{
+    expression::LicenseExpr,
+    policy::{category, LicensePolicy, UnknownAction, Verdict},
 };
+use crate::secrets::{finding_file, finding_line, mask_secret};
+use crate::store::{FindingRecord, FindingStore, JobRecord};
+use crate::utils::baseline::Baseline;
+use crate::utils::common::{find_commit_for_snippet, slack_alert, upload_to_defect_dojo};
+use crate::utils::triage::Triage;
 
 use super::common::{self, execute_command, print_error, redact_github_token};
+use super::git_auth;
+
+// Hashes of findings reported by earlier runs, without a finding store every finding is new
+async fn known_hashes(hashes: &[String], store: Option<&Arc<dyn FindingStore>>) -> HashSet<String> {
+    let store = match store {
+        Some(store) => store,
+        None => return HashSet::new(),
+    };
+    match store.known_hashes(hashes).await {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            print_error(
+                &format!(
+                    "Warning: Unable to fetch known findings from the finding store: {}",
+                    e
+                ),
+                101,
+            );
+            HashSet::new()
+        }
+    }
+}
+
+// Findings are deduplicated by their fingerprint so moving code or rebasing doesn't
+// make them new, `record` is only hashed for findings without one
+fn finding_hash(fingerprint: &str, record: &str) -> String {
+    if fingerprint.is_empty() {
+        common::hash_text(record)
+    } else {
+        fingerprint.to_string()
+    }
+}
+
+// Whether a run records its new findings as known, so later runs don't report them again.
+// Only passing runs of the main branches do unless forced with --update-baseline.
+pub struct RecordPolicy {
+    pub force: bool,
+    pub branches: Vec<String>,
+    // scanned branch, empty if unknown
+    pub branch: String,
+}
+
+impl RecordPolicy {
+    fn skip_reason(&self, failed: bool) -> Option<String> {
+        if self.force {
+            return None;
+        }
+        if failed {
+            return Some("the pipeline failed".to_string());
+        }
+        if self.branch.is_empty() {
+            return Some("the scanned branch is unknown".to_string());
+        }
+        if !self.branches.contains(&self.branch) {
+            return Some(format!(
+                "{} is not one of the baseline branches {}",
+                self.branch,
+                self.branches.join(", ")
+            ));
+        }
+        None
+    }
+}
+
+// runs after the pipeline decision so a failing run doesn't mark its findings as seen
+async fn record_findings(
+    store: Option<&Arc<dyn FindingStore>>,
+    hashes: &[String],
+    policy: &RecordPolicy,
+    repo: &str,
+    job_id: &str,
+    failed: bool,
+) {
+    let store = match store {
+        Some(store) if !hashes.is_empty() => store,
+        _ => return,
+    };
+    if let Some(reason) = policy.skip_reason(failed) {
+        println!(
+            "[+] Not recording {} new findings as known because {}, use --update-baseline to record them",
+            hashes.len(),
+            reason
+        );
+        return;
+    }
+    let mut recorded = 0;
+    for hash in hashes {
+        let record = FindingRecord {
+            hash: hash.clone(),
+            repo: repo.to_string(),
+            branch: policy.branch.clone(),
+            job_id: job_id.to_string(),
+        };
+        match store.register_finding(&record).await {
+            Ok(()) => recorded += 1,
+            Err(e) => print_error(&format!("Warning: Unable to record finding: {}", e), 101),
+        }
+    }
+    println!("[+] Recorded {} new findings as known", recorded);
+}
+
+async fn insert_job(
+    store: &Arc<dyn FindingStore>,
+    job_id: &str,
+    message: &str,
+    status: i32,
+    results: Vec<Value>,
+) {
+    let job = JobRecord {
+        job_id: job_id.to_string(),
+        message: message.to_string(),
+        status,
+        results,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = store.insert_job(&job).await {
+        print_error(&format!("Warning: Unable to insert job info: {}", e), 101);
+    }
+}
+
+// SARIF `partialFingerprints` of a finding, `baselineState` when scanning with a
+// baseline and `suppressions` of a finding suppressed by a hela:ignore comment
+// (inSource), .helaignore (external) or dismissed by triage (external)
+fn insert_sarif_state(
+    sarif_result: &mut serde_json::Map<String, Value>,
+    finding: &Value,
+    baseline: Option<&Baseline>,
+    triage: &Triage,
+) {
+    let mut fingerprints = serde_json::Map::new();
+    if let Some(fingerprint) = finding["fingerprint"].as_str() {
+        fingerprints.insert("helaFingerprint/v1".to_string(), json!(fingerprint));
+    }
+    if let Some(context_hash) = finding["context_hash"].as_str() {
+        fingerprints.insert("helaContextHash/v1".to_string(), json!(context_hash));
+    }
+    if !fingerprints.is_empty() {
+        sarif_result.insert(
+            "partialFingerprints".to_string(),
+            Value::Object(fingerprints),
+        );
+    }
+    if let Some(baseline) = baseline {
+        let state = if baseline.contains_finding(finding) {
+            "unchanged"
+        } else {
+            "new"
+        };
+        sarif_result.insert("baselineState".to_string(), json!(state));
+    }
+    let mut suppressions = Vec::new();
+    let suppression = &finding["suppression"];
+    if !suppression.is_null() {
+        suppressions.push(json!({
+            "kind": suppression["kind"],
+            "status": "accepted",
+            "justification": suppression["justification"],
+            "properties": { "location": suppression["location"] },
+        }));
+    }
+    if let Some(record) = triage.dismissal(finding) {
+        suppressions.push(json!({
+            "kind": "external",
+            "status": "accepted",
+            "justification": record.justification,
+            "properties": {
+                "triageState": record.state,
+                "owner": record.owner,
+                "expiresAt": record.expires_at,
+            },
+        }));
+    }
+    if !suppressions.is_empty() {
+        sarif_result.insert("suppressions".to_string(), Value::Array(suppressions));
+    }
+}
 
 pub async fn pipeline_failure(
     code_path: String,
@@ -19,11 +206,14 @@ pub async fn pipeline_failure(
     policy_url: String,
     slack_url: String,
     job_id: String,
-    mongo_uri: String,
+    store: Option<Arc<dyn FindingStore>>,
     defectdojo_url: String,
     defectdojo_token: String,
     product_name: String,
     engagement_name: String,
+    show_secrets: bool,
+    baseline: Option<Baseline>,
+    record_policy: RecordPolicy,
 ) {
     let mut pipline_pkgs_data = Vec::new();
     let redacted_code_path = redact_github_token(&code_path);
@@ -31,7 +221,13 @@ pub async fn pipeline_failure(
     let mut total_issues = 0;
     let mut pipeline_sast_sca_data = HashMap::new();
     let mut pipeline_secret_license_data = HashMap::new();
+    // package -> all licenses declared by the package combined in one SPDX expression
+    let mut package_licenses: Vec<(String, Option<LicenseExpr>)> = Vec::new();
+    // manifests which couldn't be scanned for licenses
+    let mut license_errors: Vec<String> = Vec::new();
     let mut found_issues = false;
+    // findings no earlier run reported, recorded once the pipeline decision is made
+    let mut new_hashes: Vec<String> = Vec::new();
     let mut found_sast_issues = false;
     let found_sca_issues = false;
     let mut found_secret_issues = false;
@@ -39,6 +235,15 @@ pub async fn pipeline_failure(
 
     let mut exit_code = 1;
     let mut exit_msg = String::new();
+    // findings ignored by hela:ignore comments or .helaignore, reported in SARIF only
+    let mut suppressed_count = 0;
+    // findings of the --baseline, they don't count towards the policy either
+    let mut baseline_count = 0;
+    let in_baseline = |finding: &Value| {
+        baseline
+            .as_ref()
+            .is_some_and(|x| x.contains_finding(finding))
+    };
 
     if !std::path::Path::new("/tmp/output.json").exists() {
         return;
@@ -47,6 +252,26 @@ pub async fn pipeline_failure(
     let json_output: serde_json::Value =
         serde_json::from_str(&original_output).expect("Error parsing JSON");
 
+    // false positives and accepted risks of `hela triage` don't count towards the policy
+    // until they expire
+    let triage = match &store {
+        Some(store) => match Triage::load(store.as_ref(), &json_output).await {
+            Ok(triage) => triage,
+            Err(e) => {
+                print_error(
+                    &format!("Warning: Unable to load triage states: {}", e),
+                    101,
+                );
+                Triage::default()
+            }
+        },
+        None => Triage::default(),
+    };
+    for warning in triage.warnings() {
+        print_error(&format!("Warning: {}", warning), 101);
+    }
+    let mut triaged_count = 0;
+
     // start preparing results here
     let mut slack_alert_msg = String::new();
 
@@ -73,6 +298,18 @@ pub async fn pipeline_failure(
         let mut sast_results: Vec<HashMap<&str, String>> = Vec::new();
 
         for result in json_output["sast"].as_array().unwrap_or(&vec![]) {
+            if !result["suppression"].is_null() {
+                suppressed_count += 1;
+                continue;
+            }
+            if in_baseline(result) {
+                baseline_count += 1;
+                continue;
+            }
+            if triage.dismissal(result).is_some() {
+                triaged_count += 1;
+                continue;
+            }
             let severity = result["extra"]["severity"]
                 .as_str()
                 .unwrap_or("unknown")
@@ -91,6 +328,10 @@ pub async fn pipeline_failure(
                 ("severity", severity.clone()),
                 ("message", result["extra"]["message"].to_string()),
                 ("lines", result["extra"]["lines"].to_string()),
+                (
+                    "fingerprint",
+                    result["fingerprint"].as_str().unwrap_or("").to_string(),
+                ),
             ]);
 
             sast_results.push(sast_result);
@@ -123,7 +364,7 @@ pub async fn pipeline_failure(
                     result["path"], result["severity"], message_without_commit
                 );
 
-                let hashed_message = common::hash_text(&vuln_record);
+                let hashed_message = finding_hash(&result["fingerprint"], &vuln_record);
                 message_to_hash.insert(
                     hashed_message,
                     (
@@ -135,13 +376,7 @@ pub async fn pipeline_failure(
             }
 
             let hashes: Vec<String> = message_to_hash.keys().cloned().collect();
-            let existing_hashes = match bulk_check_hash_exists(&hashes, &mongo_uri).await {
-                Ok(hashes) => hashes,
-                Err(e) => {
-                    eprintln!("Error fetching hashes: {}", e);
-                    return;
-                }
-            };
+            let existing_hashes = known_hashes(&hashes, store.as_ref()).await;
 
             let mut found_sast_issues = false;
             let mut sast_count = 0;
@@ -162,7 +397,7 @@ pub async fn pipeline_failure(
                     ));
 
                     table.add_row(row![sast_count, path, severity, truncated_message]);
-                    common::register_hash(&hashed_message, &mongo_uri).await;
+                    new_hashes.push(hashed_message);
                 }
             }
 
@@ -202,6 +437,18 @@ pub async fn pipeline_failure(
                     );
                     let mut vulns_list = Vec::new();
                     for vuln in package["vulnerabilities"].as_array().unwrap() {
+                        if !vuln["suppression"].is_null() {
+                            suppressed_count += 1;
+                            continue;
+                        }
+                        if in_baseline(vuln) {
+                            baseline_count += 1;
+                            continue;
+                        }
+                        if triage.dismissal(vuln).is_some() {
+                            triaged_count += 1;
+                            continue;
+                        }
                         let mut severity = match vuln["database_specific"]["severity"] {
                             Value::String(ref severity) => severity,
                             _ => "UNKNOWN",
@@ -217,6 +464,8 @@ pub async fn pipeline_failure(
                             Value::String(ref details) => details,
                             _ => "UNKNOWN",
                         };
+                        vulnerability
+                            .insert("fingerprint", vuln["fingerprint"].as_str().unwrap_or(""));
                         vulnerability.insert("summary", summary);
                         vulnerability.insert("details", details);
                         vulnerability.insert("severity", severity);
@@ -295,7 +544,7 @@ pub async fn pipeline_failure(
                         result["cwe_id"],
                         result["aliases"]
                     );
-                    let hashed_message = common::hash_text(&vuln_record);
+                    let hashed_message = finding_hash(result["fingerprint"], &vuln_record);
                     // Collect messages and their hashes along with other details
                     message_to_hash.insert(
                         hashed_message,
@@ -311,16 +560,7 @@ pub async fn pipeline_failure(
 
                 // Convert the collected hashes into a vector
                 let hashes: Vec<String> = message_to_hash.keys().cloned().collect();
-                let existing_hashes_result = bulk_check_hash_exists(&hashes, &mongo_uri).await;
-
-                // Handle the Result properly
-                let existing_hashes = match existing_hashes_result {
-                    Ok(hashes) => hashes,
-                    Err(e) => {
-                        eprintln!("Error fetching hashes: {}", e);
-                        return;
-                    }
-                };
+                let existing_hashes = known_hashes(&hashes, store.as_ref()).await;
 
                 let mut sca_count = 0;
                 let mut found_sca_issues = false;
@@ -353,7 +593,7 @@ pub async fn pipeline_failure(
                         ));
 
                         // Register the missing hash
-                        common::register_hash(&hashed_message, &mongo_uri).await;
+                        new_hashes.push(hashed_message);
                     }
                 }
 
@@ -372,28 +612,53 @@ pub async fn pipeline_failure(
     }
 
     let mut total_secrets_exposed = 0;
+    let mut total_verified_secrets_exposed = 0;
 
     if is_secret {
         let mut detected_detectors = Vec::new();
+        let mut verified_detectors = Vec::new();
         let mut secret_results = Vec::new();
         for result in json_output["secret"]["results"].as_array().unwrap() {
-            let line_number = match result["SourceMetadata"]["Data"]["Filesystem"]["line"].as_i64()
-            {
-                Some(line_number) => line_number,
-                None => 0,
-            };
-            let number_string = line_number.to_string();
+            if !result["suppression"].is_null() {
+                suppressed_count += 1;
+                continue;
+            }
+            if in_baseline(result) {
+                baseline_count += 1;
+                continue;
+            }
+            if triage.dismissal(result).is_some() {
+                triaged_count += 1;
+                continue;
+            }
+            let number_string = finding_line(result).to_string();
+            let git_data = &result["SourceMetadata"]["Data"]["Git"];
             let secret_result = {
                 let mut secret_result = HashMap::new();
+                secret_result.insert("file", finding_file(result).to_string());
+                secret_result.insert("line", number_string);
+                // set for git history scans only
                 secret_result.insert(
-                    "file",
-                    result["SourceMetadata"]["Data"]["Filesystem"]["file"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
+                    "commit",
+                    git_data["commit"].as_str().unwrap_or("").to_string(),
+                );
+                secret_result.insert(
+                    "author",
+                    git_data["email"].as_str().unwrap_or("").to_string(),
+                );
+                secret_result.insert(
+                    "present_at_head",
+                    match git_data["present_at_head"].as_bool() {
+                        Some(true) => "Yes".to_string(),
+                        Some(false) => "No".to_string(),
+                        None => String::new(),
+                    },
                 );
-                secret_result.insert("line", number_string);
                 secret_result.insert("raw", result["Raw"].as_str().unwrap_or("").to_string());
+                secret_result.insert(
+                    "fingerprint",
+                    result["fingerprint"].as_str().unwrap_or("").to_string(),
+                );
                 secret_result.insert(
                     "detector_name",
                     result["DetectorName"].as_str().unwrap_or("").to_uppercase(),
@@ -402,10 +667,21 @@ pub async fn pipeline_failure(
                     "decoder_name",
                     result["DecoderName"].as_str().unwrap_or("").to_string(),
                 );
+                secret_result.insert(
+                    "verified",
+                    if result["Verified"] == true {
+                        "Yes".to_string()
+                    } else {
+                        "No".to_string()
+                    },
+                );
                 secret_result
             };
             secret_results.push(secret_result);
             let detector_name = result["DetectorName"].as_str().unwrap_or("").to_uppercase();
+            if result["Verified"] == true && !verified_detectors.contains(&detector_name) {
+                verified_detectors.push(detector_name.clone());
+            }
             if !detected_detectors.contains(&detector_name) {
                 detected_detectors.push(detector_name);
             }
@@ -415,27 +691,38 @@ pub async fn pipeline_failure(
             .map(|x| x.to_string())
             .collect::<Vec<String>>();
         pipeline_secret_license_data.insert("detected_detectors", detected_detectors);
+        pipeline_secret_license_data.insert("verified_detectors", verified_detectors);
 
+        let is_history_scan = secret_results.iter().any(|x| !x["commit"].is_empty());
         let mut table = Table::new();
         if secret_results.clone().len() > 0 {
             println!("\n\n");
             println!("\t\t ================== Secret Results ==================");
             slack_alert_msg.push_str("\n\n");
             slack_alert_msg.push_str("================== Secret Results ==================");
-            table.add_row(
-                row![bFg->"S.No", bFg->"File", bFg->"Line", bFg->"Raw", bFg->"Detector Name", bFg->"Commit"],
-            );
+            if is_history_scan {
+                table.add_row(
+                    row![bFg->"S.No", bFg->"File", bFg->"Line", bFg->"Raw", bFg->"Detector Name", bFg->"Verified", bFg->"Commit", bFg->"Author", bFg->"At HEAD"],
+                );
+            } else {
+                table.add_row(
+                    row![bFg->"S.No", bFg->"File", bFg->"Line", bFg->"Raw", bFg->"Detector Name", bFg->"Verified", bFg->"Commit"],
+                );
+            }
         }
 
         let secret_count = 0;
-        let mut message_to_hash: HashMap<String, (String, String, String, String, String)> =
-            HashMap::new();
+        let mut message_to_hash: HashMap<String, (HashMap<&str, String>, String)> = HashMap::new();
 
         // Collect all secret records and their hashes
         for value in secret_results.clone() {
             // Append to slack alert message, remove first 2 values after split with "/"
-            let commit_id =
-                find_commit_for_snippet(&value["file"], &value["raw"].to_string()).unwrap();
+            // history scans already know the commit which introduced the secret
+            let commit_id = if value["commit"].is_empty() {
+                find_commit_for_snippet(&value["file"], &value["raw"].to_string()).unwrap()
+            } else {
+                Some(value["commit"].clone())
+            };
             let commit_base_link = commit_path.split("/commit").collect::<Vec<&str>>()[0];
             let commit_link = format!(
                 "{}/commit/{}",
@@ -449,67 +736,78 @@ pub async fn pipeline_failure(
                 "\n\nFile: {}\nLine: {}\nRaw: {}\nDetector Name: {}\nCommit: {}",
                 value["file"], value["line"], value["raw"], value["detector_name"], commit_link
             );
-            let hashed_message = common::hash_text(&vuln_record);
+            let hashed_message = finding_hash(&value["fingerprint"], &vuln_record);
 
             // Collect messages and their hashes along with other details
-            message_to_hash.insert(
-                hashed_message,
-                (
-                    value["file"].clone(),
-                    value["line"].clone(),
-                    value["raw"].clone(),
-                    value["detector_name"].clone(),
-                    commit_link,
-                ),
-            );
+            message_to_hash.insert(hashed_message, (value, commit_link));
         }
 
         // Convert the collected hashes into a vector
         let hashes: Vec<String> = message_to_hash.keys().cloned().collect();
-        let existing_hashes_result = bulk_check_hash_exists(&hashes, &mongo_uri).await;
-
-        // Handle the Result properly
-        let existing_hashes = match existing_hashes_result {
-            Ok(hashes) => hashes,
-            Err(e) => {
-                eprintln!("Error fetching hashes: {}", e);
-                return;
-            }
-        };
+        let existing_hashes = known_hashes(&hashes, store.as_ref()).await;
 
         let mut secret_count = 0;
         // Process each message to check for existence and add to the table
-        for (hashed_message, (file, line, raw, detector_name, commit_link)) in message_to_hash {
+        for (hashed_message, (value, commit_link)) in message_to_hash {
             if !existing_hashes.contains(&hashed_message) {
                 found_secret_issues = true;
                 secret_count += 1;
                 total_issues += 1;
                 total_secrets_exposed += 1;
+                if value["verified"] == "Yes" {
+                    total_verified_secrets_exposed += 1;
+                }
 
                 // Strip raw to 50 characters
-                let raw_truncated = raw.chars().take(50).collect::<String>();
+                let raw_truncated = if show_secrets {
+                    value["raw"].chars().take(50).collect::<String>()
+                } else {
+                    mask_secret(&value["raw"])
+                };
 
                 // Add row to table
-                table.add_row(row![
-                    secret_count,
-                    file,
-                    line,
-                    raw_truncated,
-                    detector_name,
-                    commit_link
-                ]);
+                if is_history_scan {
+                    table.add_row(row![
+                        secret_count,
+                        value["file"],
+                        value["line"],
+                        raw_truncated,
+                        value["detector_name"],
+                        value["verified"],
+                        commit_link,
+                        value["author"],
+                        value["present_at_head"]
+                    ]);
+                } else {
+                    table.add_row(row![
+                        secret_count,
+                        value["file"],
+                        value["line"],
+                        raw_truncated,
+                        value["detector_name"],
+                        value["verified"],
+                        commit_link
+                    ]);
+                }
 
                 slack_alert_msg.push_str(&format!(
-                    "\n\nFile: {}\nLine: {}\nRaw: {}\nDetector Name: {}\nCommit: {}",
-                    file,
-                    line,
-                    raw,
-                    detector_name,
+                    "\n\nFile: {}\nLine: {}\nRaw: {}\nDetector Name: {}\nVerified: {}\nCommit: {}",
+                    value["file"],
+                    value["line"],
+                    mask_secret(&value["raw"]),
+                    value["detector_name"],
+                    value["verified"],
                     commit_link.clone()
                 ));
+                if is_history_scan {
+                    slack_alert_msg.push_str(&format!(
+                        "\nAuthor: {}\nPresent at HEAD: {}",
+                        value["author"], value["present_at_head"]
+                    ));
+                }
 
                 // Register the missing hash
-                common::register_hash(&hashed_message, &mongo_uri).await;
+                new_hashes.push(hashed_message);
             }
         }
 
@@ -528,6 +826,14 @@ pub async fn pipeline_failure(
                 let mut license_details = HashMap::new();
                 license_details.insert(package_name, licenses.clone());
                 detected_licenses.push(license_details.get(package_name).unwrap().clone());
+                let licenses = licenses
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|x| x.as_str())
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>();
+                package_licenses.push((package_name.clone(), LicenseExpr::all_of(&licenses)));
             }
             license_results.insert(manifest, license_detail);
 
@@ -550,7 +856,7 @@ pub async fn pipeline_failure(
                 ));
             }
             let mut table = Table::new();
-            table.add_row(row![bFg->"S.No", bFg->"Package", bFg->"Licenses"]);
+            table.add_row(row![bFg->"S.No", bFg->"Package", bFg->"Licenses", bFg->"Category"]);
             let mut license_count = 0;
             for (package_name, licenses) in license_detail.as_object().unwrap() {
                 license_count += 1;
@@ -559,21 +865,83 @@ pub async fn pipeline_failure(
                 for license in licenses.as_array().unwrap() {
                     license_array.push(license.as_str().unwrap());
                 }
-                table.add_row(row![license_count, package_name, license_array.join(", ")]);
+                let mut categories = Vec::new();
+                if let Some(expression) = LicenseExpr::all_of(
+                    &license_array
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>(),
+                ) {
+                    for id in expression.license_ids() {
+                        let category = category(&id).as_str();
+                        if !categories.contains(&category) {
+                            categories.push(category);
+                        }
+                    }
+                }
+                if categories.is_empty() {
+                    categories.push("unknown");
+                }
+                let licenses_label = if license_array.is_empty() {
+                    "Unknown".to_string()
+                } else {
+                    license_array.join(", ")
+                };
+                table.add_row(row![
+                    license_count,
+                    package_name,
+                    licenses_label,
+                    categories.join(", ")
+                ]);
                 slack_alert_msg.push_str(&format!(
                     "\n\nPackage: {}\nLicenses: {}",
-                    package_name,
-                    license_array.join(", ")
+                    package_name, licenses_label
                 ));
             }
             table.printstd();
         }
+        if let Some(errors) = json_output["license_errors"].as_object() {
+            if !errors.is_empty() {
+                println!("\n\n");
+                println!("\t\t ================== License scan errors ==================");
+                slack_alert_msg
+                    .push_str("\n\n================== License scan errors ==================");
+                let mut table = Table::new();
+                table.add_row(row![bFg->"S.No", bFg->"Manifest", bFg->"Error"]);
+                for (index, (manifest, error)) in errors.iter().enumerate() {
+                    let error = error.as_str().unwrap_or("");
+                    table.add_row(row![index + 1, manifest, error]);
+                    slack_alert_msg
+                        .push_str(&format!("\n\nManifest: {}\nError: {}", manifest, error));
+                    license_errors.push(manifest.clone());
+                }
+                table.printstd();
+            }
+        }
         licenses_list = licenses_list
             .iter()
             .map(|x| x.to_lowercase())
             .collect::<Vec<String>>();
         pipeline_secret_license_data.insert("licenses", licenses_list);
     }
+    if suppressed_count > 0 {
+        println!(
+            "\n[+] {} findings suppressed by hela:ignore comments or .helaignore",
+            suppressed_count
+        );
+    }
+    if baseline_count > 0 {
+        println!(
+            "\n[+] {} known findings of the baseline are not reported",
+            baseline_count
+        );
+    }
+    if triaged_count > 0 {
+        println!(
+            "\n[+] {} findings triaged as false positive or accepted risk are not reported",
+            triaged_count
+        );
+    }
     if found_sast_issues == false
         && found_sca_issues == false
         && found_secret_issues == false
@@ -643,6 +1011,7 @@ pub async fn pipeline_failure(
                 "properties".to_string(),
                 serde_json::Value::Object(properties),
             );
+            insert_sarif_state(&mut sast_result, result, baseline.as_ref(), &triage);
             sast_results.push(serde_json::Value::Object(sast_result));
         }
         results.append(&mut sast_results);
@@ -706,6 +1075,7 @@ pub async fn pipeline_failure(
                             "properties".to_string(),
                             serde_json::Value::Object(properties),
                         );
+                        insert_sarif_state(&mut sca_result, vuln, baseline.as_ref(), &triage);
                         sca_results.push(serde_json::Value::Object(sca_result));
                     }
                 }
@@ -716,11 +1086,13 @@ pub async fn pipeline_failure(
     if is_secret {
         let mut secret_results = Vec::new();
         for result in json_output["secret"]["results"].as_array().unwrap() {
-            let file_path = result["SourceMetadata"]["Data"]["Filesystem"]["file"]
-                .as_str()
-                .unwrap();
+            let file_path = finding_file(result);
             let raw_value = result["Raw"].as_str().unwrap();
-            let commit_id = find_commit_for_snippet(file_path, raw_value).unwrap();
+            let git_data = &result["SourceMetadata"]["Data"]["Git"];
+            let commit_id = match git_data["commit"].as_str() {
+                Some(commit_id) => Some(commit_id.to_string()),
+                None => find_commit_for_snippet(file_path, raw_value).unwrap(),
+            };
             let commit_base_link = commit_path.split("/commit").collect::<Vec<&str>>()[0];
             let commit_link = format!(
                 "{}/commit/{}",
@@ -739,7 +1111,7 @@ pub async fn pipeline_failure(
             let msg = format!(
                 "Secret of {} with value {} exposed\n\nCommit: {}",
                 result["DetectorName"].as_str().unwrap(),
-                result["Raw"].as_str().unwrap(),
+                mask_secret(raw_value),
                 commit_link
             );
             let msg_val = serde_json::Value::String(msg);
@@ -751,12 +1123,7 @@ pub async fn pipeline_failure(
             let mut artifact_location = serde_json::Map::new();
             artifact_location.insert(
                 "uri".to_string(),
-                serde_json::Value::String(format!(
-                    "file://{}",
-                    result["SourceMetadata"]["Data"]["Filesystem"]["file"]
-                        .as_str()
-                        .unwrap()
-                )),
+                serde_json::Value::String(format!("file://{}", file_path)),
             );
             physical_location.insert(
                 "artifactLocation".to_string(),
@@ -774,7 +1141,14 @@ pub async fn pipeline_failure(
                 serde_json::Value::String("high".to_string()),
             );
             let mut tags = Vec::new();
-            if !result["SourceMetadata"]["Data"]["Filesystem"]["line"].is_null() {
+            if let Some(author) = git_data["email"].as_str() {
+                // the file of a history finding may not exist anymore, so git blame can't
+                // be used to find the author
+                tags.push(Value::String(author.to_string()));
+                if git_data["present_at_head"] == false {
+                    tags.push(Value::String("REMOVED".to_string()));
+                }
+            } else if !result["SourceMetadata"]["Data"]["Filesystem"]["line"].is_null() {
                 let commiter_info = get_commit_info(
                     result["SourceMetadata"]["Data"]["Filesystem"]["line"]
                         .as_u64()
@@ -792,12 +1166,16 @@ pub async fn pipeline_failure(
                     commiter_info["name"].to_string().replace("\"", ""),
                 ));
             }
+            if result["Verified"] == true {
+                tags.push(Value::String("VERIFIED".to_string()));
+            }
             tags.push(Value::String("SECRET".to_string()));
             properties.insert("tags".to_string(), serde_json::Value::Array(tags));
             secret_result.insert(
                 "properties".to_string(),
                 serde_json::Value::Object(properties),
             );
+            insert_sarif_state(&mut secret_result, result, baseline.as_ref(), &triage);
             secret_results.push(serde_json::Value::Object(secret_result));
         }
         results.append(&mut secret_results);
@@ -1014,6 +1392,16 @@ pub async fn pipeline_failure(
 
         if is_secret && secret_policy.is_some() {
             let secret_policy = secret_policy.unwrap().as_mapping().unwrap();
+            // with verified_only, only secrets a verifier confirmed to be live are counted
+            let verified_only = secret_policy
+                .get("verified_only")
+                .and_then(|x| x.as_bool())
+                .unwrap_or(false);
+            let total_secrets_exposed = if verified_only {
+                total_verified_secrets_exposed
+            } else {
+                total_secrets_exposed
+            };
             if secret_policy.contains_key(&serde_yaml::Value::String("contains".to_string())) {
                 let contains = secret_policy
                     .get(&serde_yaml::Value::String("contains".to_string()))
@@ -1021,7 +1409,11 @@ pub async fn pipeline_failure(
                     .as_sequence()
                     .unwrap();
                 let pipeline_secret_data = pipeline_secret_license_data
-                    .get("detected_detectors")
+                    .get(if verified_only {
+                        "verified_detectors"
+                    } else {
+                        "detected_detectors"
+                    })
                     .unwrap();
                 for detector in pipeline_secret_data.iter() {
                     if contains.contains(&serde_yaml::Value::String(detector.to_string())) {
@@ -1071,26 +1463,59 @@ pub async fn pipeline_failure(
             }
         }
         if is_license_compliance && license_policy.is_some() {
-            let license_policy = license_policy.unwrap().as_mapping().unwrap();
-            if license_policy.contains_key(&serde_yaml::Value::String("contains".to_string())) {
-                let contains = license_policy
-                    .get(&serde_yaml::Value::String("contains".to_string()))
-                    .unwrap()
-                    .as_sequence()
-                    .unwrap();
-                let contains = contains
-                    .iter()
-                    .map(|x| x.as_str().unwrap().to_lowercase())
-                    .collect::<Vec<String>>();
-                let pipeline_license_data = pipeline_secret_license_data.get("licenses").unwrap();
-                for license in pipeline_license_data.iter() {
-                    if contains.contains(&license.to_string().to_lowercase()) {
+            let license_policy = match LicensePolicy::from_yaml(license_policy.unwrap()) {
+                Ok(license_policy) => license_policy,
+                Err(e) => {
+                    print_error(&format!("Error: Invalid license policy: {}", e), 1);
+                    return;
+                }
+            };
+            // nothing is known about the licenses of a manifest cdxgen failed on
+            let mut unknown_packages = license_errors
+                .iter()
+                .map(|x| format!("{} (license scan failed)", x))
+                .collect::<Vec<String>>();
+            for (package_name, expression) in package_licenses.iter() {
+                match license_policy.evaluate(package_name, expression.as_ref()) {
+                    Verdict::Allowed => {}
+                    Verdict::Excepted(reason) => {
+                        println!(
+                            "[+] License policy exception applied for {}: {}",
+                            package_name, reason
+                        );
+                    }
+                    Verdict::Unknown => unknown_packages.push(package_name.clone()),
+                    Verdict::Blocked(blocked) => {
+                        // a package is only a problem if there is no way to use it without a blocked
+                        // license, e.g. "MIT OR GPL-3.0-only" is fine as long as MIT is not blocked
+                        is_pipeline_failed = true;
+                        pipeline_failure_reason = format!(
+                            "Pipeline failed because {} license is not allowed by the policy (package {} is licensed under {})",
+                            blocked.join(", "),
+                            package_name,
+                            expression.as_ref().unwrap()
+                        );
+                    }
+                }
+            }
+            if !unknown_packages.is_empty() {
+                match license_policy.unknown {
+                    UnknownAction::Fail => {
                         is_pipeline_failed = true;
                         pipeline_failure_reason = format!(
-                            "Pipeline failed because {} license is present in blocked list",
-                            license
+                            "Pipeline failed because license of {} packages could not be determined: {}",
+                            unknown_packages.len(),
+                            unknown_packages.join(", ")
+                        );
+                    }
+                    UnknownAction::Warn => {
+                        println!(
+                            "[❕] Warning: license of {} packages could not be determined: {}",
+                            unknown_packages.len(),
+                            unknown_packages.join(", ")
                         );
                     }
+                    UnknownAction::Allow => {}
                 }
             }
             if is_pipeline_failed {
@@ -1102,18 +1527,20 @@ pub async fn pipeline_failure(
             println!("\n\n");
             println!("\t\t ================== ❌ Pipeline Failed ==================");
             println!("\t\t Reason: {}", pipeline_failure_reason);
+            record_findings(
+                store.as_ref(),
+                &new_hashes,
+                &record_policy,
+                &cleaned_code_path,
+                &job_id,
+                true,
+            )
+            .await;
             if !job_id.is_empty() {
                 println!("\t\t Job ID: {}", job_id);
-                if !mongo_uri.is_empty() {
-                    println!("\t\t Inserting job info into MongoDB");
-                    let _ = insert_job_info(
-                        &mongo_uri,
-                        &job_id,
-                        &pipeline_failure_reason,
-                        &exit_code,
-                        results,
-                    )
-                    .await;
+                if let Some(store) = &store {
+                    println!("\t\t Inserting job info into the finding store");
+                    insert_job(store, &job_id, &pipeline_failure_reason, exit_code, results).await;
                 }
             }
 
@@ -1131,15 +1558,24 @@ pub async fn pipeline_failure(
             // finish everything and smoothly exit
             exit(0);
         } else {
+            record_findings(
+                store.as_ref(),
+                &new_hashes,
+                &record_policy,
+                &cleaned_code_path,
+                &job_id,
+                false,
+            )
+            .await;
             if !job_id.is_empty() {
                 println!("\t\t Job ID: {}", job_id);
-                if !mongo_uri.is_empty() {
-                    println!("\t\t Inserting job info into MongoDB");
-                    let _ = insert_job_info(
-                        &mongo_uri,
+                if let Some(store) = &store {
+                    println!("\t\t Inserting job info into the finding store");
+                    insert_job(
+                        store,
                         &job_id,
                         &pipeline_failure_reason,
-                        &exit_code,
+                        exit_code,
                         results.clone(),
                     )
                     .await;
@@ -1170,14 +1606,25 @@ pub async fn pipeline_failure(
                 println!("[+] No issues found in scan results, so slack alert is not sent");
             }
         }
-        let _ = insert_job_info(
-            &mongo_uri,
+        record_findings(
+            store.as_ref(),
+            &new_hashes,
+            &record_policy,
+            &cleaned_code_path,
             &job_id,
-            "No policy file provided, skipping policy check",
-            &exit_code,
-            results.clone(),
+            false,
         )
         .await;
+        if let Some(store) = &store {
+            insert_job(
+                store,
+                &job_id,
+                "No policy file provided, skipping policy check",
+                exit_code,
+                results.clone(),
+            )
+            .await;
+        }
         println!("\n\n");
     }
 
@@ -1312,7 +1759,8 @@ async fn get_commit_info_from_github(path: &str, repo_url_with_pat: &str) -> Opt
     // Parse the repository URL with PAT
     println!("Fetching commit info from GitHub API for {}", path);
     let repo_url = reqwest::Url::parse(repo_url_with_pat).ok()?;
-    let pat = repo_url.username();
+    // the code path has no credentials when the git auth providers are used
+    let pat = git_auth::token().unwrap_or(repo_url.username().to_string());
     let host = repo_url.host_str().unwrap_or("github.com");
 
     // Extract the owner and repository name from the path
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::fingerprint::output_fingerprints;
+use crate::store::{FindingStore, TriageRecord};
+
+pub const STATES: [&str; 5] = [
+    "open",
+    "confirmed",
+    "false-positive",
+    "accepted-risk",
+    "fixed",
+];
+
+// states which keep a finding out of the policy counts until they expire
+pub fn is_dismissal(state: &str) -> bool {
+    state == "false-positive" || state == "accepted-risk"
+}
+
+pub fn is_expired(record: &TriageRecord) -> bool {
+    DateTime::parse_from_rfc3339(&record.expires_at).is_ok_and(|x| x < Utc::now())
+}
+
+// Triage records of the findings of a scan
+#[derive(Default)]
+pub struct Triage {
+    records: HashMap<String, TriageRecord>,
+}
+
+impl Triage {
+    pub async fn load(store: &dyn FindingStore, output: &Value) -> Result<Triage, String> {
+        let fingerprints = output_fingerprints(output);
+        if fingerprints.is_empty() {
+            return Ok(Triage::default());
+        }
+        let records = store.triage_records(Some(&fingerprints)).await?;
+        Ok(Triage {
+            records: records.into_iter().map(|x| (x.hash.clone(), x)).collect(),
+        })
+    }
+
+    // record of a false positive or accepted risk which hasn't expired
+    pub fn dismissal(&self, finding: &Value) -> Option<&TriageRecord> {
+        let record = self.records.get(finding["fingerprint"].as_str()?)?;
+        if is_dismissal(&record.state) && !is_expired(record) {
+            Some(record)
+        } else {
+            None
+        }
+    }
+
+    // expired dismissals and fixed findings which are still present
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .records
+            .values()
+            .filter_map(|record| {
+                if is_dismissal(&record.state) && is_expired(record) {
+                    Some(format!(
+                        "{} of finding {} expired on {}, it counts towards the policy again",
+                        record.state, record.hash, record.expires_at
+                    ))
+                } else if record.state == "fixed" {
+                    Some(format!(
+                        "Finding {} is marked fixed but still present",
+                        record.hash
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        warnings.sort();
+        warnings
+    }
+}